@@ -0,0 +1,141 @@
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScrollAnchor {
+    pub heading: Option<HeadingAnchor>,
+    pub source_line: usize,
+    pub line_delta: usize,
+    pub row_offset: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeadingAnchor {
+    pub title: String,
+    pub source_line: usize,
+}
+
+pub struct AnchorTarget {
+    pub line: usize,
+    pub row_offset: usize,
+}
+
+pub fn source_line_numbers(source: &str, ranges: &[Range<usize>]) -> Vec<usize> {
+    let bytes = source.as_bytes();
+    let mut numbers = Vec::with_capacity(ranges.len());
+    let mut pos = 0usize;
+    let mut line = 0usize;
+    for range in ranges {
+        let target = range.start.min(bytes.len());
+        if target < pos {
+            line -= bytes[target..pos].iter().filter(|&&b| b == b'\n').count();
+        } else {
+            line += bytes[pos..target].iter().filter(|&&b| b == b'\n').count();
+        }
+        pos = target;
+        numbers.push(line);
+    }
+    numbers
+}
+
+/// `headings` pairs each heading's rendered line index with its title.
+pub fn capture(
+    source_lines: &[usize],
+    headings: &[(usize, String)],
+    line: usize,
+    row_offset: usize,
+) -> ScrollAnchor {
+    let source_line = source_lines.get(line).copied().unwrap_or(0);
+    let first_with_source = source_lines[..line.min(source_lines.len())]
+        .iter()
+        .rposition(|&n| n != source_line)
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    let heading = headings
+        .iter()
+        .rev()
+        .find(|(heading_line, _)| *heading_line <= line)
+        .map(|(heading_line, title)| HeadingAnchor {
+            title: title.clone(),
+            source_line: source_lines.get(*heading_line).copied().unwrap_or(0),
+        });
+    ScrollAnchor {
+        heading,
+        source_line,
+        line_delta: line.saturating_sub(first_with_source),
+        row_offset,
+    }
+}
+
+pub fn resolve(
+    anchor: &ScrollAnchor,
+    source_lines: &[usize],
+    headings: &[(usize, String)],
+) -> AnchorTarget {
+    if source_lines.is_empty() {
+        return AnchorTarget {
+            line: 0,
+            row_offset: 0,
+        };
+    }
+    let mut target_source = anchor.source_line;
+    if let Some(heading) = &anchor.heading {
+        let moved = headings
+            .iter()
+            .filter(|(_, title)| *title == heading.title)
+            .filter_map(|(line, _)| source_lines.get(*line).copied())
+            .min_by_key(|&source_line| source_line.abs_diff(heading.source_line));
+        if let Some(new_heading_line) = moved {
+            let offset = anchor.source_line.saturating_sub(heading.source_line);
+            target_source = new_heading_line + offset;
+        }
+    }
+    let first = source_lines
+        .partition_point(|&n| n < target_source)
+        .min(source_lines.len() - 1);
+    let mut line = first;
+    let mut row_offset = 0;
+    if source_lines[first] == target_source {
+        let same_source = source_lines[first..]
+            .iter()
+            .take_while(|&&n| n == target_source)
+            .count();
+        line = first + anchor.line_delta.min(same_source - 1);
+        row_offset = anchor.row_offset;
+    }
+    AnchorTarget { line, row_offset }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_line_numbers_follow_ranges() {
+        let source = "a\nb\nc\n";
+        let ranges = vec![0..1, 2..3, 2..3, 4..5];
+        assert_eq!(source_line_numbers(source, &ranges), vec![0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn anchor_follows_heading_when_text_is_inserted_above() {
+        let headings = vec![(2, String::from("Usage"))];
+        let before = vec![0, 1, 4, 5, 6, 7];
+        let anchor = capture(&before, &headings, 4, 1);
+        assert_eq!(anchor.source_line, 6);
+
+        let shifted_headings = vec![(5, String::from("Usage"))];
+        let after = vec![0, 1, 2, 3, 4, 7, 8, 9, 10];
+        let target = resolve(&anchor, &after, &shifted_headings);
+        assert_eq!(target.line, 7);
+        assert_eq!(target.row_offset, 1);
+    }
+
+    #[test]
+    fn anchor_keeps_position_inside_multi_line_blocks() {
+        let lines = vec![0, 2, 2, 2, 2, 6];
+        let anchor = capture(&lines, &[], 3, 0);
+        assert_eq!(anchor.line_delta, 2);
+        let target = resolve(&anchor, &lines, &[]);
+        assert_eq!(target.line, 3);
+    }
+}
@@ -1,206 +1,1794 @@
 use std::{
-    fs, io,
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    mem,
+    ops::Range,
     path::{Path, PathBuf},
+    sync::mpsc::{Receiver, TryRecvError},
+    time::SystemTime,
 };
 
+use crate::anchor::{self, ScrollAnchor};
+use crate::clipboard::{Clipboard, Copied};
+use crate::color::ColorDepth;
+use crate::graphics::{Graphics, Placement, Protocol};
+use crate::history::{History, HistoryEntry};
+use crate::keymap::{Action, KeyPress, KeyResult, Keymap, ACTIONS, STATUS_HINTS};
+use crate::links::{self, LinkTarget};
 use crate::markdown::{
-    heading_block_colors, line_row_span, markdown_to_render_with_options, CodeBlockOverlay,
-    HeadingOverlay, MarkdownOptions, RenderedMarkdown, CODE_BLOCK_BG, CODE_BLOCK_BORDER_FG,
+    line_row_span, markdown_to_render_with_options, slice_line, wrap_rows, CodeBlockOverlay,
+    HeadingOverlay, ImageOverlay, LinkOverlay, MarkdownOptions, RenderedMarkdown, TableOverlay,
 };
+use crate::marks::{JumpList, Marks};
+use crate::outline::{self, OutlineEntry, OutlineState};
+use crate::search::{self, SearchState};
+use crate::state::Positions;
+use crate::theme::Theme;
+use crossterm::event::{KeyCode, KeyEvent};
+use pulldown_cmark::HeadingLevel;
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
+    widgets::{
+        block::Title, Block, Borders, Clear, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Wrap,
+    },
     Frame,
 };
 
+/// Columns moved by one `h` / `l` in an unwrapped or zoomed block.
+pub const SIDEWAYS_STEP: usize = 4;
+
 pub struct App {
     path: PathBuf,
     source: String,
     content: Vec<Line<'static>>,
+    line_sources: Vec<Range<usize>>,
+    source_lines: Vec<usize>,
     headings: Vec<HeadingOverlay>,
     code_blocks: Vec<CodeBlockOverlay>,
+    tables: Vec<TableOverlay>,
+    /// Blocks shown at their natural width, with how far each is scrolled
+    /// sideways.
+    no_wrap: HashMap<BlockId, usize>,
     rules: Vec<usize>,
+    links: Vec<LinkOverlay>,
+    images: Vec<ImageOverlay>,
+    focused_link: Option<usize>,
     table_width: usize,
+    table_width_limit: Option<usize>,
+    wrap_width: Option<usize>,
+    heading_bands: bool,
+    theme: Theme,
+    color_depth: ColorDepth,
     scroll: usize,
+    cursor_offset: usize,
+    half_page: Option<usize>,
     viewport_height: u16,
     viewport_width: u16,
     status: Option<String>,
     show_help: bool,
+    zoom: Option<Zoom>,
+    search: SearchState,
+    watch: bool,
+    file_stamp: Option<FileStamp>,
+    outline: OutlineState,
+    keymap: Keymap,
+    mark_prompt: Option<Action>,
+    /// Where a line selection started; it runs to the cursor line.
+    selection: Option<usize>,
+    marks: Marks,
+    jumps: JumpList,
+    history: History,
+    positions: Positions,
+    stdin_source: Option<String>,
+    mouse: bool,
+    layout: ViewLayout,
+    dragging_scrollbar: bool,
+    graphics: Graphics,
+    clipboard: Clipboard,
+    /// OSC 52 sequences waiting to be written after the next draw.
+    pending_clipboard: String,
+    clipboard_job: Option<Receiver<io::Result<()>>>,
 }
 
 impl App {
-    pub fn load(path: &Path) -> io::Result<Self> {
-        let markdown = fs::read_to_string(path)?;
-        let options = MarkdownOptions::default();
-        let render = markdown_to_render_with_options(&markdown, options);
-        Ok(Self::new(
-            path.to_path_buf(),
-            markdown,
-            render,
-            options.max_table_width,
-        ))
+    /// Opens `path`, scrolled to where `positions` says it was last read.
+    pub fn load(path: &Path, theme: Theme, positions: Positions) -> io::Result<Self> {
+        let markdown = read_markdown(path)?;
+        let options = MarkdownOptions {
+            theme,
+            ..MarkdownOptions::default()
+        };
+        let render = markdown_to_render_with_options(&markdown, options.clone());
+        let stdin_source = is_stdin(path).then(|| markdown.clone());
+        let mut app = Self::new(path.to_path_buf(), markdown, render, options);
+        app.stdin_source = stdin_source;
+        app.positions = positions;
+        app.restore_saved_position();
+        Ok(app)
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        is_stdin(&self.path)
     }
 
     pub fn new(
         path: PathBuf,
         source: String,
         render: RenderedMarkdown,
-        table_width: usize,
+        options: MarkdownOptions,
     ) -> Self {
-        Self {
-            path,
+        let mut app = Self {
             source,
-            headings: render.headings,
-            code_blocks: render.code_blocks,
-            rules: render.rules,
-            content: ensure_non_empty(render.lines),
-            table_width,
+            content: Vec::new(),
+            line_sources: Vec::new(),
+            source_lines: Vec::new(),
+            headings: Vec::new(),
+            code_blocks: Vec::new(),
+            tables: Vec::new(),
+            no_wrap: HashMap::new(),
+            rules: Vec::new(),
+            links: Vec::new(),
+            images: Vec::new(),
+            focused_link: None,
+            table_width: options.max_table_width,
+            table_width_limit: None,
+            wrap_width: None,
+            heading_bands: true,
+            theme: options.theme,
+            color_depth: ColorDepth::TrueColor,
             scroll: 0,
+            cursor_offset: 0,
+            half_page: None,
             viewport_height: 0,
             viewport_width: 80,
-            status: Some(String::from("Press ? for help, q to quit")),
+            status: Some(greeting(&Keymap::default())),
             show_help: false,
-        }
+            zoom: None,
+            search: SearchState::default(),
+            file_stamp: FileStamp::read(&path),
+            path,
+            watch: false,
+            outline: OutlineState::default(),
+            keymap: Keymap::default(),
+            mark_prompt: None,
+            selection: None,
+            marks: Marks::default(),
+            jumps: JumpList::default(),
+            history: History::default(),
+            positions: Positions::default(),
+            stdin_source: None,
+            mouse: false,
+            layout: ViewLayout::default(),
+            dragging_scrollbar: false,
+            graphics: Graphics::new(None),
+            clipboard: Clipboard::default(),
+            pending_clipboard: String::new(),
+            clipboard_job: None,
+        };
+        app.apply_render(render);
+        app
     }
 
     pub fn reload(&mut self) -> io::Result<()> {
+        let anchor = self.capture_anchor();
+        self.read_from_disk()?;
+        self.restore_anchor(&anchor);
+        Ok(())
+    }
+
+    pub fn set_watch(&mut self, watch: bool) {
+        self.watch = watch && !self.is_stdin();
+        self.file_stamp = FileStamp::read(&self.path);
+    }
+
+    pub fn toggle_watch(&mut self) {
+        if self.is_stdin() {
+            self.set_status("Watch mode needs a file; this document was read from stdin");
+            return;
+        }
+        self.set_watch(!self.watch);
+        if self.watch {
+            self.set_status("Watching file for changes");
+        } else {
+            self.set_status("Stopped watching file");
+        }
+    }
+
+    pub fn poll_file_changes(&mut self) {
+        if !self.watch {
+            return;
+        }
+        // Editors that save via a temp file and rename leave a short window where
+        // the path is missing; keep the old stamp and pick up the new file next tick.
+        let Some(stamp) = FileStamp::read(&self.path) else {
+            return;
+        };
+        if self.file_stamp == Some(stamp) {
+            return;
+        }
+        match self.reload() {
+            Ok(()) => self.set_status("File changed on disk, reloaded"),
+            Err(err) => self.set_status(format!("Reload failed: {err}")),
+        }
+    }
+
+    fn read_from_disk(&mut self) -> io::Result<()> {
+        if self.is_stdin() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "input was read from stdin, there is no file to reload",
+            ));
+        }
+        let stamp = FileStamp::read(&self.path);
         let markdown = fs::read_to_string(&self.path)?;
-        let width = self.table_width.max(1);
-        let options = MarkdownOptions {
-            max_table_width: width,
+        self.graphics.invalidate();
+        self.zoom = None;
+        self.selection = None;
+        // Blocks are numbered by position, so an edit above an unwrapped
+        // block would hand its state to another one. Follow the markdown
+        // of each block instead; blocks that changed are wrapped again.
+        let unwrapped: Vec<(BlockId, String, usize)> = self
+            .no_wrap
+            .iter()
+            .filter_map(|(&block, &offset)| {
+                let text = block_text(
+                    &self.source,
+                    &self.tables,
+                    &self.code_blocks,
+                    &self.line_sources,
+                    block,
+                )?;
+                Some((block, text.to_string(), offset))
+            })
+            .collect();
+        self.no_wrap.clear();
+        let mut render = markdown_to_render_with_options(&markdown, self.markdown_options());
+        for (block, text, offset) in unwrapped {
+            let count = match block {
+                BlockId::Table(_) => render.tables.len(),
+                BlockId::Code(_) => render.code_blocks.len(),
+            };
+            let moved = (0..count)
+                .map(|idx| match block {
+                    BlockId::Table(_) => BlockId::Table(idx),
+                    BlockId::Code(_) => BlockId::Code(idx),
+                })
+                .find(|candidate| {
+                    !self.no_wrap.contains_key(candidate)
+                        && block_text(
+                            &markdown,
+                            &render.tables,
+                            &render.code_blocks,
+                            &render.line_sources,
+                            *candidate,
+                        ) == Some(text.as_str())
+                });
+            if let Some(moved) = moved {
+                self.no_wrap.insert(moved, offset);
+            }
+        }
+        if self
+            .no_wrap
+            .keys()
+            .any(|block| matches!(block, BlockId::Table(_)))
+        {
+            render = markdown_to_render_with_options(&markdown, self.markdown_options());
+        }
+        self.source = markdown;
+        self.apply_render(render);
+        self.file_stamp = stamp;
+        Ok(())
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.size());
+
+        let viewer_block = Block::default()
+            .title(self.title_line())
+            .title(Title::from(self.progress_line()).alignment(Alignment::Right))
+            .borders(Borders::ALL)
+            .padding(Padding::horizontal(1));
+
+        let mut viewport = layout[0];
+        let mut outline_area = None;
+        if self.outline.is_open() {
+            let outline_width = outline::width_for(viewport.width);
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(outline_width), Constraint::Min(1)])
+                .split(viewport);
+            viewport = columns[1];
+            outline_area = Some(columns[0]);
+            let entries = self.outline_entries();
+            let current = self.current_heading();
+            outline::render_outline(
+                frame,
+                columns[0],
+                &entries,
+                current,
+                &mut self.outline,
+                &self.theme,
+            );
+        }
+        let mut inner = viewer_block.inner(viewport);
+        if let Some(wrap_width) = self.wrap_width {
+            inner.width = inner
+                .width
+                .min(wrap_width.clamp(1, u16::MAX as usize) as u16);
+        }
+        self.layout = ViewLayout {
+            outline: outline_area,
+            viewer: viewport,
+            inner,
+        };
+        self.viewport_height = inner.height.max(1);
+        let width = inner.width.max(1) as usize;
+        self.ensure_table_width(width);
+        self.scroll = self.scroll.min(self.max_scroll());
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+
+        let paragraph = Paragraph::new(self.display_lines())
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll as u16, 0));
+        frame.render_widget(viewer_block, viewport);
+        frame.render_widget(paragraph, inner);
+
+        if self.heading_bands {
+            self.highlight_headings(frame, inner, &metrics);
+        }
+        self.render_rules(frame, inner, &metrics);
+        self.render_code_blocks(frame, inner, &metrics);
+        self.render_selection(frame, inner, &metrics);
+        self.render_images(frame, inner, &metrics);
+        self.render_cursor(frame, inner);
+        self.render_scrollbar(frame, viewport);
+
+        let status = Paragraph::new(self.status_line()).wrap(Wrap { trim: true });
+        frame.render_widget(status, layout[1]);
+
+        if let Some(zoom) = self.zoom.as_mut() {
+            zoom.render(frame, frame.size(), &self.theme, &self.keymap);
+        }
+        if self.show_help {
+            self.render_help(frame, frame.size());
+        }
+        self.color_depth.downsample_buffer(frame.buffer_mut());
+    }
+
+    pub fn set_color_depth(&mut self, depth: ColorDepth) {
+        self.color_depth = depth;
+    }
+
+    pub fn set_wrap_width(&mut self, wrap_width: Option<usize>) {
+        self.wrap_width = wrap_width;
+    }
+
+    pub fn set_table_width_limit(&mut self, limit: Option<usize>) {
+        self.table_width_limit = limit;
+    }
+
+    pub fn set_heading_bands(&mut self, show: bool) {
+        self.heading_bands = show;
+    }
+
+    /// Picks how images are drawn (`None` keeps text placeholders) and
+    /// re-renders so the right number of lines is reserved for them.
+    pub fn set_images(&mut self, protocol: Option<Protocol>) {
+        let anchor = self.capture_anchor();
+        self.graphics = Graphics::new(protocol);
+        let render = markdown_to_render_with_options(&self.source, self.markdown_options());
+        self.apply_render(render);
+        self.restore_anchor(&anchor);
+    }
+
+    /// Writes kitty or sixel images queued by the last `draw`.
+    pub fn flush_graphics(&mut self, out: &mut impl io::Write) -> io::Result<()> {
+        self.graphics.flush(out)
+    }
+
+    pub fn graphics_need_clear(&self) -> bool {
+        self.graphics.needs_clear()
+    }
+
+    pub fn forget_shown_graphics(&mut self) {
+        self.graphics.forget_shown();
+    }
+
+    pub fn clear_graphics(&mut self, out: &mut impl io::Write) -> io::Result<()> {
+        self.graphics.clear(out)
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Clipboard) {
+        self.clipboard = clipboard;
+    }
+
+    /// Hands `text` to the clipboard and reports `what` was copied.
+    fn copy_text(&mut self, text: &str, what: &str) {
+        match self.clipboard.copy(text) {
+            Ok(copied) => {
+                match copied {
+                    Copied::Done => {}
+                    Copied::Escape(sequence) => self.pending_clipboard.push_str(&sequence),
+                    Copied::Running(done) => self.clipboard_job = Some(done),
+                }
+                self.set_status(format!("Copied {what} to {}", self.clipboard.target()));
+            }
+            Err(err) => self.set_status(format!("Copy failed: {err}")),
+        }
+    }
+
+    /// Reports a clipboard command that failed after `copy_text` returned.
+    pub fn poll_clipboard(&mut self) {
+        let Some(done) = &self.clipboard_job else {
+            return;
+        };
+        match done.try_recv() {
+            Err(TryRecvError::Empty) => return,
+            Ok(Err(err)) => self.set_status(format!("Copy failed: {err}")),
+            Ok(Ok(())) | Err(TryRecvError::Disconnected) => {}
+        }
+        self.clipboard_job = None;
+    }
+
+    /// Writes OSC 52 sequences queued since the last call.
+    pub fn flush_clipboard(&mut self, out: &mut impl io::Write) -> io::Result<()> {
+        if self.pending_clipboard.is_empty() {
+            return Ok(());
+        }
+        out.write_all(mem::take(&mut self.pending_clipboard).as_bytes())?;
+        out.flush()
+    }
+
+    /// Copies the source of the zoomed code block, or else of the code
+    /// block nearest the top of the screen.
+    pub fn copy_code_block(&mut self) {
+        let zoomed = self.zoom.as_ref().and_then(|zoom| {
+            let code = zoom.code.clone()?;
+            Some((code, zoom.title.clone()))
+        });
+        let found = zoomed.or_else(|| {
+            let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+            let first = metrics.line_at_row(self.scroll);
+            let last = metrics.line_at_row(self.scroll + self.visible_rows().saturating_sub(1));
+            // Blocks are in document order, so the first one on screen is
+            // the nearest; its borders sit on the blank lines around it.
+            let block = self.code_blocks.iter().find(|block| {
+                block.line_start.saturating_sub(1) <= last && block.line_end >= first
+            })?;
+            let language = block.language.as_deref().unwrap_or("code");
+            Some((block.source.clone(), language.to_string()))
+        });
+        let Some((code, language)) = found else {
+            self.set_status("No code block on screen");
+            return;
+        };
+        let count = code.lines().count();
+        let plural = if count == 1 { "" } else { "s" };
+        self.copy_text(&code, &format!("{count} line{plural} of {language}"));
+    }
+
+    pub fn is_selecting(&self) -> bool {
+        self.selection.is_some()
+    }
+
+    /// Starts selecting lines at the cursor, or drops the selection.
+    pub fn toggle_selection(&mut self) {
+        if self.selection.take().is_some() {
+            return;
+        }
+        self.selection = Some(self.cursor_line());
+        self.set_status(format!(
+            "Move to extend the selection; {} copies it as shown, {} as markdown",
+            self.keymap.key_for(Action::Yank),
+            self.keymap.key_for(Action::YankSource)
+        ));
+    }
+
+    pub fn cancel_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Copies the selected lines, or the cursor line without a selection,
+    /// either as rendered or as the markdown they came from.
+    pub fn yank(&mut self, markdown: bool) {
+        let lines = self.selected_lines();
+        let text = if markdown {
+            self.source_for_lines(lines).to_string()
+        } else {
+            self.content[lines]
+                .iter()
+                .map(|line| format!("{}\n", search::line_text(line).trim_end()))
+                .collect()
+        };
+        self.selection = None;
+        let count = text.lines().count();
+        let plural = if count == 1 { "" } else { "s" };
+        let kind = if markdown { "markdown" } else { "text" };
+        self.copy_text(&text, &format!("{count} line{plural} of {kind}"));
+    }
+
+    /// Content lines from the selection start to the cursor, inclusive.
+    fn selected_lines(&self) -> Range<usize> {
+        let cursor = self.cursor_line();
+        let start = self
+            .selection
+            .unwrap_or(cursor)
+            .min(self.content.len().saturating_sub(1));
+        start.min(cursor)..start.max(cursor) + 1
+    }
+
+    /// The markdown behind `lines`, widened to whole source lines. Code
+    /// blocks whose border is selected bring their fences along, and any
+    /// table row brings the whole table.
+    fn source_for_lines(&self, lines: Range<usize>) -> &str {
+        let mut covered: Option<Range<usize>> = None;
+        let mut cover = |range: &Range<usize>| {
+            if !range.is_empty() {
+                covered = Some(match covered.take() {
+                    Some(seen) => seen.start.min(range.start)..seen.end.max(range.end),
+                    None => range.clone(),
+                });
+            }
+        };
+        let mut borders = Vec::new();
+        for block in &self.code_blocks {
+            let border = [block.line_start.saturating_sub(1), block.line_end];
+            if border.iter().any(|line| lines.contains(line)) {
+                cover(&block.source_range);
+                borders.extend(border);
+            }
+        }
+        // A border row shares its source with the block beside it, which
+        // must not come along when only the fence was meant.
+        for line in lines.filter(|line| !borders.contains(line)) {
+            cover(&self.line_sources[line]);
+        }
+        let Some(range) = covered else {
+            return "";
+        };
+        let end = range.end.min(self.source.len());
+        let start = self.source[..range.start.min(end)]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let end = if self.source[..end].ends_with('\n') {
+            end
+        } else {
+            self.source[end..]
+                .find('\n')
+                .map_or(self.source.len(), |newline| end + newline + 1)
+        };
+        &self.source[start..end]
+    }
+
+    /// The content line under the cursor.
+    fn cursor_line(&self) -> usize {
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        metrics.line_at_row(self.scroll + self.cursor_row())
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        if rows == 0 {
+            return;
+        }
+        self.scroll = self.scroll.saturating_sub(rows);
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        if rows == 0 {
+            return;
+        }
+        self.scroll = self.scroll.saturating_add(rows).min(self.max_scroll());
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.viewport_height.max(1) as usize);
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.viewport_height.max(1) as usize);
+    }
+
+    pub fn scroll_to(&mut self, row: usize) {
+        self.scroll = row.min(self.max_scroll());
+    }
+
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = self.max_scroll();
+    }
+
+    /// Scrolls like `scroll_down` but keeps the cursor on its document row
+    /// until it would leave the screen.
+    pub fn view_down(&mut self, rows: usize) {
+        let before = self.scroll;
+        self.scroll_down(rows);
+        self.cursor_offset = self.cursor_offset.saturating_sub(self.scroll - before);
+    }
+
+    pub fn view_up(&mut self, rows: usize) {
+        let before = self.scroll;
+        self.scroll_up(rows);
+        self.cursor_offset = (self.cursor_offset + before - self.scroll)
+            .min((self.viewport_height as usize).saturating_sub(1));
+    }
+
+    /// Half a page, or `count` rows; like vim the count sticks for later calls.
+    pub fn half_page_down(&mut self, count: Option<usize>) {
+        let rows = self.half_page_rows(count);
+        self.scroll_down(rows);
+    }
+
+    pub fn half_page_up(&mut self, count: Option<usize>) {
+        let rows = self.half_page_rows(count);
+        self.scroll_up(rows);
+    }
+
+    fn half_page_rows(&mut self, count: Option<usize>) -> usize {
+        if count.is_some() {
+            self.half_page = count;
+        }
+        self.half_page
+            .unwrap_or(self.viewport_height as usize / 2)
+            .max(1)
+    }
+
+    pub fn scroll_to_percent(&mut self, percent: usize) {
+        let max_scroll = self.max_scroll();
+        self.scroll_to((max_scroll * percent.min(100)).div_ceil(100));
+    }
+
+    /// Scrolls `row` into view and puts the cursor on it.
+    fn goto_row(&mut self, row: usize) {
+        let row = row.min(self.total_rows().saturating_sub(1));
+        self.scroll_to(row);
+        self.cursor_offset = row - self.scroll;
+    }
+
+    /// Jumps to the first rendered line that comes from 1-based source `line`.
+    pub fn goto_source_line(&mut self, line: usize) {
+        let target = line.saturating_sub(1);
+        let content_line = self
+            .source_lines
+            .iter()
+            .position(|&source| source >= target)
+            .unwrap_or(self.content.len().saturating_sub(1));
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        if let Some((row, _)) = metrics.line_range(content_line, content_line + 1) {
+            self.goto_row(row);
+        }
+    }
+
+    /// `H`: the cursor goes `count` rows below the top of the screen.
+    pub fn screen_top(&mut self, count: usize) {
+        self.cursor_offset = count.saturating_sub(1);
+    }
+
+    pub fn screen_middle(&mut self) {
+        self.cursor_offset = self.visible_rows().saturating_sub(1) / 2;
+    }
+
+    /// `L`: the cursor goes `count` rows above the bottom of the screen.
+    pub fn screen_bottom(&mut self, count: usize) {
+        self.cursor_offset = self.visible_rows().saturating_sub(count.max(1));
+    }
+
+    fn visible_rows(&self) -> usize {
+        (self.viewport_height.max(1) as usize).min(self.total_rows().saturating_sub(self.scroll))
+    }
+
+    /// The cursor's row on screen, kept inside the visible part of the document.
+    fn cursor_row(&self) -> usize {
+        self.cursor_offset
+            .min(self.visible_rows().saturating_sub(1))
+    }
+
+    pub fn is_mouse_enabled(&self) -> bool {
+        self.mouse
+    }
+
+    pub fn set_mouse(&mut self, mouse: bool) {
+        self.mouse = mouse;
+        self.dragging_scrollbar = false;
+    }
+
+    pub fn toggle_mouse(&mut self) {
+        self.set_mouse(!self.mouse);
+        if self.mouse {
+            self.set_status("Mouse enabled (hold Shift to select text)");
+        } else {
+            self.set_status("Mouse disabled, terminal selection restored");
+        }
+    }
+
+    pub fn mouse_down(&mut self, column: u16, row: u16) {
+        if self.on_scrollbar(column, row) {
+            self.dragging_scrollbar = true;
+            self.drag_scrollbar(row);
+            return;
+        }
+        if let Some(outline) = self.layout.outline {
+            if contains(outline, column, row) {
+                self.click_outline(outline, row);
+                return;
+            }
+        }
+        if contains(self.layout.inner, column, row) {
+            self.click_document(column, row);
+        }
+    }
+
+    pub fn mouse_drag(&mut self, row: u16) {
+        if self.dragging_scrollbar {
+            self.drag_scrollbar(row);
+        }
+    }
+
+    pub fn mouse_up(&mut self) {
+        self.dragging_scrollbar = false;
+    }
+
+    fn on_scrollbar(&self, column: u16, row: u16) -> bool {
+        let viewer = self.layout.viewer;
+        let inner = self.layout.inner;
+        viewer.width > 0
+            && column == viewer.x + viewer.width - 1
+            && row >= inner.y
+            && row < inner.y + inner.height
+    }
+
+    fn drag_scrollbar(&mut self, row: u16) {
+        let inner = self.layout.inner;
+        let track = inner.height.saturating_sub(1).max(1) as usize;
+        let offset = row
+            .saturating_sub(inner.y)
+            .min(inner.height.saturating_sub(1)) as usize;
+        let target = (self.max_scroll() * offset + track / 2) / track;
+        self.scroll_to(target);
+    }
+
+    fn click_outline(&mut self, area: Rect, row: u16) {
+        if row <= area.y || row + 1 >= area.y + area.height {
+            return;
+        }
+        let idx = self.outline.offset() + (row - area.y - 1) as usize;
+        if idx >= self.headings.len() {
+            return;
+        }
+        self.outline.focus(idx);
+        self.outline_jump();
+    }
+
+    fn click_document(&mut self, column: u16, row: u16) {
+        let width = self.viewport_width.max(1) as usize;
+        let metrics = self.compute_line_metrics(width);
+        let doc_row = self.scroll + (row - self.layout.inner.y) as usize;
+        let line_idx = metrics.line_at_row(doc_row);
+        let wrapped_row = doc_row.saturating_sub(metrics.offsets[line_idx]);
+        let column = (column - self.layout.inner.x) as usize;
+        if let Some(idx) = self.link_at(line_idx, wrapped_row, column, width) {
+            self.focused_link = Some(idx);
+            self.follow_link();
+        }
+    }
+
+    /// The link drawn at `column` of the `wrapped_row`th row of a line,
+    /// found by laying the line out with the document's word wrap.
+    fn link_at(
+        &self,
+        line_idx: usize,
+        wrapped_row: usize,
+        column: usize,
+        width: usize,
+    ) -> Option<usize> {
+        let line = self.content.get(line_idx)?;
+        // Unwrapped tables are one row, shifted by their sideways scroll.
+        let shift = self.no_wrap.iter().find_map(|(&block, &offset)| {
+            let lines = self.block_lines(block)?;
+            lines
+                .contains(&line_idx)
+                .then(|| offset.min(self.max_sideways_offset(block)))
+        });
+        let (row, column, width) = match shift {
+            Some(shift) => (0, column + shift, usize::MAX),
+            None => (wrapped_row, column, width),
         };
-        let render = markdown_to_render_with_options(&markdown, options);
+        let mut start = 0;
+        let offset = wrap_rows(line, width)
+            .get(row)?
+            .iter()
+            .find_map(|&(offset, ch_width)| {
+                start += ch_width;
+                (column < start).then_some(offset)
+            })?;
+        self.links.iter().position(|link| {
+            link.spans
+                .iter()
+                .any(|span| span.line == line_idx && (span.start..span.end).contains(&offset))
+        })
+    }
+
+    pub fn set_status<T: Into<String>>(&mut self, msg: T) {
+        self.status = Some(msg.into());
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    pub fn is_help_open(&self) -> bool {
+        self.show_help
+    }
+
+    pub fn is_outline_focused(&self) -> bool {
+        self.outline.is_focused()
+    }
+
+    pub fn show_outline(&mut self) {
+        let current = self.current_heading().unwrap_or(0);
+        self.outline.open(current);
+        self.outline.unfocus();
+    }
+
+    pub fn toggle_outline(&mut self) {
+        let current = self.current_heading().unwrap_or(0);
+        if !self.outline.is_open() {
+            self.outline.open(current);
+        } else if !self.outline.is_focused() {
+            self.outline.focus(current);
+        } else {
+            self.outline.close();
+        }
+    }
+
+    pub fn unfocus_outline(&mut self) {
+        self.outline.unfocus();
+    }
+
+    pub fn outline_move(&mut self, delta: isize) {
+        self.outline.move_selection(delta, self.headings.len());
+    }
+
+    pub fn outline_select_edge(&mut self, last: bool) {
+        let idx = if last { usize::MAX } else { 0 };
+        self.outline.select(idx, self.headings.len());
+    }
+
+    pub fn outline_jump(&mut self) {
+        if let Some(heading) = self.headings.get(self.outline.selected()).copied() {
+            self.scroll_to_line(heading.line);
+        }
+        self.outline.unfocus();
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        if self.status.as_deref() == Some(greeting(&self.keymap).as_str()) {
+            self.status = Some(greeting(&keymap));
+        }
+        self.keymap = keymap;
+    }
+
+    /// Maps a key press to an action and its count, or `None` while a count
+    /// or chord is incomplete.
+    pub fn resolve_key(&mut self, key: &KeyEvent) -> Option<(Action, Option<usize>)> {
+        match self.keymap.press(KeyPress::from_event(key)) {
+            KeyResult::Action(action, count) => Some((action, count)),
+            KeyResult::Pending | KeyResult::Unbound => None,
+        }
+    }
+
+    /// Starts waiting for the letter that follows `SetMark` or `JumpMark`.
+    pub fn begin_mark_prompt(&mut self, action: Action) {
+        self.mark_prompt = Some(action);
+    }
+
+    pub fn is_mark_prompt_active(&self) -> bool {
+        self.mark_prompt.is_some()
+    }
+
+    pub fn finish_mark_prompt(&mut self, key: &KeyEvent) {
+        let Some(action) = self.mark_prompt.take() else {
+            return;
+        };
+        let name = match key.code {
+            KeyCode::Char(ch) if ch.is_ascii_alphabetic() => ch,
+            KeyCode::Esc => return,
+            _ => {
+                self.set_status("Marks are named by a letter, a-z or A-Z");
+                return;
+            }
+        };
+        if action == Action::SetMark {
+            self.marks.set(name, &self.path, self.capture_anchor());
+            self.set_status(format!("Set mark '{name}'"));
+        } else {
+            self.jump_to_mark(name);
+        }
+    }
+
+    fn jump_to_mark(&mut self, name: char) {
+        let Some(entry) = self.marks.get(name, &self.path) else {
+            self.set_status(format!("Mark '{name}' is not set"));
+            return;
+        };
+        if entry.path != self.path {
+            if let Err(err) = self.open_document(&entry.path) {
+                self.set_status(format!("Could not open {}: {err}", entry.path.display()));
+                return;
+            }
+        } else {
+            self.record_jump();
+        }
+        if let Some(anchor) = &entry.anchor {
+            self.restore_anchor(anchor);
+        }
+        self.set_status(format!("Mark '{name}'"));
+    }
+
+    /// Remembers the current position before a jump so `jump_back` can return.
+    pub fn record_jump(&mut self) {
+        let here = self.capture_anchor();
+        self.jumps.record(here);
+    }
+
+    /// Records `here`, captured before a jump, unless the jump went nowhere.
+    pub fn record_jump_from(&mut self, here: ScrollAnchor) {
+        if self.capture_anchor() != here {
+            self.jumps.record(here);
+        }
+    }
+
+    pub fn jump_back(&mut self) {
+        match self.jumps.back(self.capture_anchor()) {
+            Some(anchor) => self.restore_anchor(&anchor),
+            None => self.set_status("Already at the oldest jump"),
+        }
+    }
+
+    pub fn jump_forward(&mut self) {
+        match self.jumps.forward() {
+            Some(anchor) => self.restore_anchor(&anchor),
+            None => self.set_status("Already at the newest jump"),
+        }
+    }
+
+    pub fn next_heading(&mut self, max_level: HeadingLevel) {
+        self.jump_heading(true, max_level);
+    }
+
+    pub fn prev_heading(&mut self, max_level: HeadingLevel) {
+        self.jump_heading(false, max_level);
+    }
+
+    fn jump_heading(&mut self, forward: bool, max_level: HeadingLevel) {
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        let rows = self
+            .headings
+            .iter()
+            .filter(|heading| heading.level <= max_level)
+            .filter_map(|heading| {
+                metrics
+                    .line_range(heading.line, heading.line + 1)
+                    .map(|(row, _)| row)
+            });
+        let target = if forward {
+            rows.into_iter().find(|&row| row > self.scroll)
+        } else {
+            rows.into_iter().rev().find(|&row| row < self.scroll)
+        };
+        match target {
+            Some(_) if forward && self.scroll >= self.max_scroll() => {
+                self.set_status("Last heading is already in view")
+            }
+            Some(row) => self.scroll_to(row),
+            None if forward => self.set_status("No more headings below"),
+            None => self.set_status("No more headings above"),
+        }
+    }
+
+    pub fn has_focused_link(&self) -> bool {
+        self.focused_link.is_some()
+    }
+
+    pub fn next_link(&mut self) {
+        self.step_link(true);
+    }
+
+    pub fn prev_link(&mut self) {
+        self.step_link(false);
+    }
+
+    pub fn clear_link_focus(&mut self) {
+        if self.focused_link.take().is_some() {
+            self.set_status("Link focus cleared");
+        }
+    }
+
+    fn step_link(&mut self, forward: bool) {
+        let count = self.links.len();
+        if count == 0 {
+            self.set_status("No links in this document");
+            return;
+        }
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        let height = self.viewport_height.max(1) as usize;
+        let row_of = |link: &LinkOverlay| {
+            metrics
+                .line_range(link.spans[0].line, link.spans[0].line + 1)
+                .map(|(row, _)| row)
+                .unwrap_or(0)
+        };
+        let visible = |row: usize| row >= self.scroll && row < self.scroll + height;
+        let next = match self.focused_link {
+            Some(idx) if visible(row_of(&self.links[idx])) => {
+                if forward {
+                    (idx + 1) % count
+                } else {
+                    (idx + count - 1) % count
+                }
+            }
+            _ if forward => self
+                .links
+                .iter()
+                .position(|link| row_of(link) >= self.scroll)
+                .unwrap_or(0),
+            _ => self
+                .links
+                .iter()
+                .rposition(|link| row_of(link) < self.scroll + height)
+                .unwrap_or(count - 1),
+        };
+        self.focused_link = Some(next);
+        let row = row_of(&self.links[next]);
+        if !visible(row) {
+            self.scroll_to(row.saturating_sub(height / 3));
+        }
+        self.set_status(format!(
+            "Link {}/{count}: {}  (Enter to open)",
+            next + 1,
+            self.links[next].destination
+        ));
+    }
+
+    pub fn follow_link(&mut self) {
+        let Some(link) = self.focused_link.and_then(|idx| self.links.get(idx)) else {
+            return;
+        };
+        let destination = link.destination.clone();
+        match links::resolve(&self.base_dir(), &destination) {
+            LinkTarget::Fragment(fragment) => {
+                if !self.jump_to_fragment(&fragment) {
+                    self.set_status(format!("No heading matches #{fragment}"));
+                }
+            }
+            LinkTarget::Document { path, fragment } => match self.open_document(&path) {
+                Ok(()) => {
+                    self.set_status(format!("Opened {}", path.display()));
+                    if let Some(fragment) = fragment {
+                        self.jump_to_fragment(&fragment);
+                    }
+                }
+                Err(err) => self.set_status(format!("Could not open {}: {err}", path.display())),
+            },
+            LinkTarget::External(target) => match links::open_external(&target) {
+                Ok(()) => self.set_status(format!("Opened {target} externally")),
+                Err(err) => self.set_status(format!("Could not open {target}: {err}")),
+            },
+        }
+    }
+
+    pub fn open_document(&mut self, path: &Path) -> io::Result<()> {
+        let current = self.history_entry();
+        self.load_document(path)?;
+        self.history.visit(current);
+        self.enter_position(None);
+        Ok(())
+    }
+
+    pub fn queue_documents(&mut self, paths: Vec<PathBuf>) {
+        if !paths.is_empty() {
+            let count = paths.len();
+            self.history.queue(paths);
+            self.set_status(format!(
+                "{count} more document(s) queued, Alt+Right to open the next"
+            ));
+        }
+    }
+
+    pub fn go_back(&mut self) {
+        let current = self.history_entry();
+        let Some(target) = self.history.back(current) else {
+            self.set_status("No previous document");
+            return;
+        };
+        match self.load_document(&target.path) {
+            Ok(()) => self.enter_history_entry(&target),
+            Err(err) => {
+                self.set_status(format!("Could not open {}: {err}", target.path.display()));
+                self.history.restore_back(target);
+            }
+        }
+    }
+
+    pub fn go_forward(&mut self) {
+        let current = self.history_entry();
+        let Some(target) = self.history.forward(current) else {
+            self.set_status("No next document");
+            return;
+        };
+        match self.load_document(&target.path) {
+            Ok(()) => self.enter_history_entry(&target),
+            Err(err) => {
+                self.set_status(format!("Could not open {}: {err}", target.path.display()));
+                self.history.restore_forward(target);
+            }
+        }
+    }
+
+    fn restore_saved_position(&mut self) {
+        if self.is_stdin() {
+            return;
+        }
+        if let Some(anchor) = self.positions.get(&self.path).cloned() {
+            self.restore_anchor(&anchor);
+        }
+    }
+
+    fn remember_position(&mut self) {
+        if !self.is_stdin() {
+            let anchor = self.capture_anchor();
+            self.positions.record(&self.path, anchor);
+        }
+    }
+
+    /// Records the current document's position and writes the state file.
+    pub fn save_positions(&mut self) -> io::Result<()> {
+        self.remember_position();
+        self.positions.save()
+    }
+
+    fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            path: self.path.clone(),
+            anchor: Some(self.capture_anchor()),
+        }
+    }
+
+    fn enter_history_entry(&mut self, entry: &HistoryEntry) {
+        self.enter_position(entry.anchor.as_ref());
+        self.set_status(format!("Opened {}", entry.path.display()));
+    }
+
+    /// Places a freshly loaded document at `anchor`, or where it was last
+    /// read when there is none.
+    fn enter_position(&mut self, anchor: Option<&ScrollAnchor>) {
+        match anchor {
+            Some(anchor) => self.restore_anchor(anchor),
+            None => {
+                self.scroll = 0;
+                self.restore_saved_position();
+            }
+        }
+    }
+
+    fn load_document(&mut self, path: &Path) -> io::Result<()> {
+        self.remember_position();
+        let markdown = match (&self.stdin_source, is_stdin(path)) {
+            (Some(source), true) => source.clone(),
+            _ => fs::read_to_string(path)?,
+        };
+        self.no_wrap.clear();
+        self.zoom = None;
+        self.selection = None;
+        let render = markdown_to_render_with_options(&markdown, self.markdown_options());
+        self.path = path.to_path_buf();
         self.source = markdown;
+        self.file_stamp = FileStamp::read(&self.path);
         self.apply_render(render);
-        self.scroll = 0;
+        self.jumps.clear();
         Ok(())
     }
 
-    pub fn draw(&mut self, frame: &mut Frame<'_>) {
-        let layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Length(1)])
-            .split(frame.size());
-
-        let viewer_block = Block::default()
-            .title(self.title_line())
-            .borders(Borders::ALL)
-            .padding(Padding::horizontal(1));
+    fn jump_to_fragment(&mut self, fragment: &str) -> bool {
+        let wanted = fragment.to_lowercase();
+        let target = self
+            .heading_titles()
+            .into_iter()
+            .find(|(_, title)| links::heading_slug(title) == wanted);
+        match target {
+            Some((line, _)) => {
+                self.scroll_to_line(line);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        if let Some((row_start, _)) = metrics.line_range(line, line + 1) {
+            self.scroll_to(row_start);
+        }
+    }
+
+    fn current_heading(&self) -> Option<usize> {
+        let top_line = self.line_at_row(self.scroll);
+        self.headings
+            .iter()
+            .rposition(|heading| heading.line <= top_line)
+            .or(if self.headings.is_empty() {
+                None
+            } else {
+                Some(0)
+            })
+    }
+
+    fn outline_entries(&self) -> Vec<OutlineEntry> {
+        self.headings
+            .iter()
+            .zip(self.heading_titles())
+            .map(|(heading, (_, title))| OutlineEntry {
+                level: heading.level,
+                title,
+            })
+            .collect()
+    }
+
+    pub fn is_search_input_active(&self) -> bool {
+        self.search.is_editing()
+    }
+
+    pub fn begin_search(&mut self) {
+        self.search.begin_input(self.scroll);
+        self.set_status("/");
+    }
+
+    pub fn push_search_char(&mut self, ch: char) {
+        if let Some(input) = self.search.input_mut() {
+            input.push(ch);
+        }
+        self.update_search_input();
+    }
+
+    pub fn pop_search_char(&mut self) {
+        let emptied = match self.search.input_mut() {
+            Some(input) => input.pop().is_none(),
+            None => return,
+        };
+        if emptied {
+            self.cancel_search();
+            return;
+        }
+        self.update_search_input();
+    }
+
+    pub fn confirm_search(&mut self) {
+        self.search.finish_input();
+        if self.search.query().is_empty() {
+            self.search.clear();
+            self.set_status("Search cleared");
+            return;
+        }
+        let origin = self.search.origin_scroll();
+        if origin != self.scroll {
+            self.jumps.record(self.anchor_at(origin));
+        }
+        self.set_match_status();
+    }
+
+    pub fn cancel_search(&mut self) {
+        let origin = self.search.origin_scroll();
+        self.search.clear();
+        self.scroll_to(origin);
+        self.set_status("Search cancelled");
+    }
+
+    pub fn next_match(&mut self) {
+        self.step_match(true);
+    }
+
+    pub fn prev_match(&mut self) {
+        self.step_match(false);
+    }
+
+    fn update_search_input(&mut self) {
+        let query = self.search.input().unwrap_or_default().to_string();
+        self.search.update(&query, &self.content);
+        let origin_line = self.line_at_row(self.search.origin_scroll());
+        match self.search.first_from_line(origin_line) {
+            Some(idx) => self.jump_to_match(idx),
+            None => self.scroll_to(self.search.origin_scroll()),
+        }
+        let count = self.search.matches().len();
+        let suffix = if query.is_empty() {
+            String::new()
+        } else if count == 0 {
+            String::from("  (no matches)")
+        } else {
+            format!("  ({count} matches)")
+        };
+        self.set_status(format!("/{query}{suffix}"));
+    }
+
+    fn step_match(&mut self, forward: bool) {
+        if self.search.query().is_empty() {
+            self.set_status("No active search (press / to search)");
+            return;
+        }
+        let count = self.search.matches().len();
+        if count == 0 {
+            self.set_status(format!("Pattern not found: {}", self.search.query()));
+            return;
+        }
+        let (next, wrapped) = match self.search.current() {
+            Some(idx) if forward => ((idx + 1) % count, idx + 1 == count),
+            Some(idx) => ((idx + count - 1) % count, idx == 0),
+            None => {
+                let top_line = self.line_at_row(self.scroll);
+                let idx = self.search.first_from_line(top_line).unwrap_or(0);
+                (idx, false)
+            }
+        };
+        self.jump_to_match(next);
+        if wrapped {
+            let edge = if forward { "top" } else { "bottom" };
+            self.set_status(format!(
+                "Search wrapped to {edge}  (match {}/{count})",
+                next + 1
+            ));
+        } else {
+            self.set_match_status();
+        }
+    }
+
+    fn jump_to_match(&mut self, idx: usize) {
+        let Some(found) = self.search.select(idx) else {
+            return;
+        };
+        let width = self.viewport_width.max(1) as usize;
+        let metrics = self.compute_line_metrics(width);
+        let Some((row_start, _)) = metrics.line_range(found.line, found.line + 1) else {
+            return;
+        };
+        let column = search::match_column(&self.content[found.line], &found);
+        let row = if self.reveal_column(found.line, column) {
+            row_start
+        } else {
+            let rows = wrap_rows(&self.content[found.line], width);
+            let in_row = rows
+                .iter()
+                .position(|row| row.last().is_some_and(|&(offset, _)| offset >= found.start));
+            row_start + in_row.unwrap_or(0)
+        };
+        let height = self.viewport_height.max(1) as usize;
+        if row < self.scroll || row >= self.scroll + height {
+            self.scroll_to(row.saturating_sub(height / 3));
+        }
+    }
+
+    fn set_match_status(&mut self) {
+        let count = self.search.matches().len();
+        match self.search.current() {
+            Some(idx) if count > 0 => self.set_status(format!(
+                "/{}  (match {}/{count})",
+                self.search.query(),
+                idx + 1
+            )),
+            _ => self.set_status(format!("Pattern not found: {}", self.search.query())),
+        }
+    }
+
+    fn line_at_row(&self, row: usize) -> usize {
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        metrics.line_at_row(row)
+    }
+
+    fn display_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = self.content.clone();
+        self.highlight_matches(&mut lines);
+        if let Some(link) = self.focused_link.and_then(|idx| self.links.get(idx)) {
+            let style = Style::default().add_modifier(Modifier::REVERSED);
+            for span in &link.spans {
+                if let Some(line) = lines.get_mut(span.line) {
+                    *line = search::highlight_line(line, &[(span.start, span.end, style)]);
+                }
+            }
+        }
+        for (&block, &offset) in &self.no_wrap {
+            let Some(range) = self.block_lines(block) else {
+                continue;
+            };
+            let offset = offset.min(self.max_sideways_offset(block));
+            let view = self.block_view_width(block);
+            for line in &mut lines[range] {
+                *line = match block {
+                    BlockId::Table(_) => slice_line(line, offset, view),
+                    BlockId::Code(_) => {
+                        let mut sliced = slice_line(line, 0, 1);
+                        sliced
+                            .spans
+                            .extend(slice_line(line, 1 + offset, view).spans);
+                        sliced
+                    }
+                };
+            }
+        }
+        lines
+    }
+
+    fn highlight_matches(&self, lines: &mut [Line<'static>]) {
+        let matches = self.search.matches();
+        let current = self.search.current();
+        let mut idx = 0usize;
+        while idx < matches.len() {
+            let line_idx = matches[idx].line;
+            let mut ranges = Vec::new();
+            while idx < matches.len() && matches[idx].line == line_idx {
+                let found = matches[idx];
+                ranges.push((
+                    found.start,
+                    found.end,
+                    if current == Some(idx) {
+                        self.theme.ui.current_match
+                    } else {
+                        self.theme.ui.search_match
+                    },
+                ));
+                idx += 1;
+            }
+            if let Some(line) = lines.get_mut(line_idx) {
+                *line = search::highlight_line(line, &ranges);
+            }
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.total_rows()
+            .saturating_sub(self.viewport_height as usize)
+    }
+
+    fn total_rows(&self) -> usize {
+        let width = self.viewport_width.max(1) as usize;
+        self.line_rows(width).sum()
+    }
+
+    fn compute_line_metrics(&self, width: usize) -> LineMetrics {
+        let mut offsets = Vec::with_capacity(self.content.len() + 1);
+        offsets.push(0);
+        let mut total = 0usize;
+        for rows in self.line_rows(width) {
+            total += rows;
+            offsets.push(total);
+        }
+        LineMetrics { offsets }
+    }
+
+    /// Rows each line takes at `width`; lines of unwrapped blocks never wrap.
+    fn line_rows(&self, width: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut unwrapped = vec![false; self.content.len()];
+        for block in self.no_wrap.keys() {
+            if let Some(lines) = self.block_lines(*block) {
+                unwrapped[lines].fill(true);
+            }
+        }
+        self.content
+            .iter()
+            .zip(unwrapped)
+            .map(move |(line, unwrapped)| {
+                if unwrapped {
+                    1
+                } else {
+                    line_row_span(line, width) as usize
+                }
+            })
+    }
+
+    fn block_lines(&self, block: BlockId) -> Option<Range<usize>> {
+        let (start, end) = match block {
+            BlockId::Table(idx) => {
+                let table = self.tables.get(idx)?;
+                (table.line_start, table.line_end)
+            }
+            BlockId::Code(idx) => {
+                let code = self.code_blocks.get(idx)?;
+                (code.line_start, code.line_end)
+            }
+        };
+        let end = end.min(self.content.len());
+        (start < end).then_some(start..end)
+    }
 
-        let viewport = layout[0];
-        let inner = viewer_block.inner(viewport);
-        self.viewport_height = inner.height.max(1);
-        let width = inner.width.max(1) as usize;
-        self.ensure_table_width(width);
-        self.viewport_width = width as u16;
+    /// The on-screen block closest to the cursor.
+    fn target_block(&self, unwrapped_only: bool) -> Option<BlockId> {
         let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        let cursor = metrics.line_at_row(self.scroll + self.cursor_row());
+        let first = metrics.line_at_row(self.scroll);
+        let last = metrics.line_at_row(self.scroll + self.visible_rows().saturating_sub(1));
+        (0..self.tables.len())
+            .map(BlockId::Table)
+            .chain((0..self.code_blocks.len()).map(BlockId::Code))
+            .filter(|block| !unwrapped_only || self.no_wrap.contains_key(block))
+            .filter_map(|block| Some((block, self.block_lines(block)?)))
+            .filter(|(_, lines)| lines.start <= last && lines.end > first)
+            .min_by_key(|(_, lines)| {
+                if cursor < lines.start {
+                    lines.start - cursor
+                } else {
+                    cursor.saturating_sub(lines.end - 1)
+                }
+            })
+            .map(|(block, _)| block)
+    }
 
-        let paragraph = Paragraph::new(self.content.clone())
-            .wrap(Wrap { trim: false })
-            .scroll((self.scroll as u16, 0))
-            .block(viewer_block);
-        frame.render_widget(paragraph, viewport);
-
-        self.highlight_headings(frame, inner, &metrics);
-        self.render_rules(frame, inner, &metrics);
-        self.render_code_blocks(frame, inner, &metrics);
-
-        let status = Paragraph::new(self.status_line()).wrap(Wrap { trim: true });
-        frame.render_widget(status, layout[1]);
-
-        if self.show_help {
-            self.render_help(frame, frame.size());
+    /// Columns of the block that fit on screen; code keeps its first column
+    /// and the last one for the border.
+    fn block_view_width(&self, block: BlockId) -> usize {
+        let width = self.viewport_width.max(1) as usize;
+        match block {
+            BlockId::Table(_) => width,
+            BlockId::Code(_) => width.saturating_sub(2).max(1),
         }
     }
 
-    pub fn scroll_up(&mut self, rows: usize) {
-        if rows == 0 {
-            return;
-        }
-        self.scroll = self.scroll.saturating_sub(rows);
+    fn max_sideways_offset(&self, block: BlockId) -> usize {
+        let Some(lines) = self.block_lines(block) else {
+            return 0;
+        };
+        let gutter = match block {
+            BlockId::Table(_) => 0,
+            BlockId::Code(_) => 1,
+        };
+        let widest = self.content[lines]
+            .iter()
+            .map(|line| line.width().saturating_sub(gutter))
+            .max()
+            .unwrap_or(0);
+        widest.saturating_sub(self.block_view_width(block))
     }
 
-    pub fn scroll_down(&mut self, rows: usize) {
-        if rows == 0 {
+    pub fn toggle_wrap(&mut self) {
+        let Some(block) = self.target_block(false) else {
+            self.set_status("No table or code block on screen");
             return;
+        };
+        let anchor = self.capture_anchor();
+        let wrapped = self.no_wrap.remove(&block).is_some();
+        if !wrapped {
+            self.no_wrap.insert(block, 0);
+        }
+        if let BlockId::Table(_) = block {
+            let render = markdown_to_render_with_options(&self.source, self.markdown_options());
+            self.apply_render(render);
+        }
+        self.restore_anchor(&anchor);
+        let kind = match block {
+            BlockId::Table(_) => "table",
+            BlockId::Code(_) => "code block",
+        };
+        if wrapped {
+            self.set_status(format!("Wrapping the {kind} again"));
+        } else {
+            self.set_status(format!(
+                "Showing the {kind} at full width ({}/{} scroll sideways)",
+                self.keymap.key_for(Action::ScrollLeft),
+                self.keymap.key_for(Action::ScrollRight)
+            ));
         }
-        self.scroll = self.scroll.saturating_add(rows).min(self.max_scroll());
     }
 
-    pub fn page_up(&mut self) {
-        self.scroll_up(self.viewport_height.max(1) as usize);
+    pub fn scroll_left(&mut self, count: usize) {
+        self.scroll_sideways(-(count.saturating_mul(SIDEWAYS_STEP) as isize));
     }
 
-    pub fn page_down(&mut self) {
-        self.scroll_down(self.viewport_height.max(1) as usize);
+    pub fn scroll_right(&mut self, count: usize) {
+        self.scroll_sideways(count.saturating_mul(SIDEWAYS_STEP) as isize);
     }
 
-    pub fn scroll_to(&mut self, row: usize) {
-        self.scroll = row.min(self.max_scroll());
+    fn scroll_sideways(&mut self, columns: isize) {
+        let Some(block) = self.target_block(true) else {
+            self.set_status(format!(
+                "Press {} on a table or code block to stop wrapping it, then scroll sideways",
+                self.keymap.key_for(Action::ToggleWrap)
+            ));
+            return;
+        };
+        let max = self.max_sideways_offset(block);
+        if let Some(offset) = self.no_wrap.get_mut(&block) {
+            *offset = offset.saturating_add_signed(columns).min(max);
+        }
     }
 
-    pub fn scroll_to_end(&mut self) {
-        self.scroll = self.max_scroll();
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom.is_some()
     }
 
-    pub fn set_status<T: Into<String>>(&mut self, msg: T) {
-        self.status = Some(msg.into());
+    /// Opens the table or code block under the cursor in a full-screen
+    /// popup; tables are laid out again at their natural width.
+    pub fn zoom_block(&mut self) {
+        let cursor = self.cursor_line();
+        let found = self.code_blocks.iter().position(|block| {
+            // The borders sit on the blank lines around the code.
+            (block.line_start.saturating_sub(1)..=block.line_end).contains(&cursor)
+        });
+        let zoom = if let Some(idx) = found {
+            let block = &self.code_blocks[idx];
+            let lines = self.content[block.line_start..block.line_end.min(self.content.len())]
+                .iter()
+                .map(|line| slice_line(line, 1, usize::MAX))
+                .collect();
+            let title = block.language.as_deref().unwrap_or("code");
+            Zoom::new(title, lines, Some(block.source.clone()))
+        } else if let Some(idx) = self
+            .tables
+            .iter()
+            .position(|table| (table.line_start..table.line_end).contains(&cursor))
+        {
+            let mut options = self.markdown_options();
+            options.no_wrap_tables.insert(idx);
+            let mut render = markdown_to_render_with_options(&self.source, options);
+            let Some(table) = render.tables.get(idx).copied() else {
+                return;
+            };
+            let lines = render
+                .lines
+                .drain(table.line_start..table.line_end)
+                .collect();
+            Zoom::new("table", lines, None)
+        } else {
+            self.set_status(format!(
+                "Put the cursor (▸) on a table or code block and press {} to zoom it",
+                self.keymap.key_for(Action::Open)
+            ));
+            return;
+        };
+        self.zoom = Some(zoom);
     }
 
-    pub fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
+    pub fn close_zoom(&mut self) {
+        self.zoom = None;
     }
 
-    pub fn is_help_open(&self) -> bool {
-        self.show_help
+    /// Scrolls the zoomed block by `rows` and `columns`, clamped to its size.
+    pub fn zoom_move(&mut self, rows: isize, columns: isize) {
+        if let Some(zoom) = self.zoom.as_mut() {
+            zoom.scroll = zoom.scroll.saturating_add_signed(rows);
+            zoom.column = zoom.column.saturating_add_signed(columns);
+            zoom.clamp();
+        }
     }
 
-    fn max_scroll(&self) -> usize {
-        self.total_rows()
-            .saturating_sub(self.viewport_height as usize)
+    /// Rows visible in the zoom popup, for paging.
+    pub fn zoom_page_rows(&self) -> usize {
+        self.zoom.as_ref().map_or(1, |zoom| zoom.view.1.max(1))
     }
 
-    fn total_rows(&self) -> usize {
-        let width = self.viewport_width.max(1) as usize;
-        self.content
-            .iter()
-            .map(|line| line_row_span(line, width) as usize)
-            .sum()
+    /// Scrolls the unwrapped block holding `line` so `column` is on screen;
+    /// returns false when `line` wraps normally.
+    fn reveal_column(&mut self, line: usize, column: usize) -> bool {
+        let Some(block) = self.no_wrap.keys().copied().find(|block| {
+            self.block_lines(*block)
+                .is_some_and(|lines| lines.contains(&line))
+        }) else {
+            return false;
+        };
+        let column = match block {
+            BlockId::Table(_) => column,
+            BlockId::Code(_) => column.saturating_sub(1),
+        };
+        let view = self.block_view_width(block);
+        let max = self.max_sideways_offset(block);
+        if let Some(offset) = self.no_wrap.get_mut(&block) {
+            if column < *offset || column >= *offset + view {
+                *offset = column.saturating_sub(view / 3).min(max);
+            }
+        }
+        true
     }
 
-    fn compute_line_metrics(&self, width: usize) -> LineMetrics {
-        let mut offsets = Vec::with_capacity(self.content.len() + 1);
-        offsets.push(0);
-        let mut total = 0usize;
-        for line in &self.content {
-            total += line_row_span(line, width) as usize;
-            offsets.push(total);
+    fn markdown_options(&self) -> MarkdownOptions {
+        MarkdownOptions {
+            max_table_width: self.table_width.max(1),
+            theme: self.theme,
+            image_dir: self.graphics.protocol().map(|_| self.base_dir()),
+            image_sizes: self.graphics.sizes(),
+            no_wrap_tables: self
+                .no_wrap
+                .keys()
+                .filter_map(|block| match block {
+                    BlockId::Table(idx) => Some(*idx),
+                    BlockId::Code(_) => None,
+                })
+                .collect(),
         }
-        LineMetrics { offsets }
+    }
+
+    fn base_dir(&self) -> PathBuf {
+        self.path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
     }
 
     fn ensure_table_width(&mut self, width: usize) {
         let width = width.max(1);
-        if width == self.table_width {
+        let table_width = self
+            .table_width_limit
+            .map_or(width, |limit| width.min(limit.max(1)));
+        if table_width == self.table_width && width == self.viewport_width as usize {
             return;
         }
-        let options = MarkdownOptions {
-            max_table_width: width,
+        let anchor = self.capture_anchor();
+        if table_width != self.table_width {
+            self.table_width = table_width;
+            let render = markdown_to_render_with_options(&self.source, self.markdown_options());
+            self.apply_render(render);
+        }
+        self.viewport_width = width.min(u16::MAX as usize) as u16;
+        self.restore_anchor(&anchor);
+    }
+
+    pub fn capture_anchor(&self) -> ScrollAnchor {
+        self.anchor_at(self.scroll)
+    }
+
+    fn anchor_at(&self, row: usize) -> ScrollAnchor {
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        let line = metrics.line_at_row(row);
+        let row_offset = row.saturating_sub(metrics.offsets[line]);
+        anchor::capture(&self.source_lines, &self.heading_titles(), line, row_offset)
+    }
+
+    pub fn restore_anchor(&mut self, anchor: &ScrollAnchor) {
+        let target = anchor::resolve(anchor, &self.source_lines, &self.heading_titles());
+        let metrics = self.compute_line_metrics(self.viewport_width.max(1) as usize);
+        let Some((row_start, row_end)) = metrics.line_range(target.line, target.line + 1) else {
+            self.scroll_to(0);
+            return;
         };
-        let render = markdown_to_render_with_options(&self.source, options);
-        self.apply_render(render);
-        self.table_width = width;
-        self.scroll = self.scroll.min(self.max_scroll());
+        let row_offset = target.row_offset.min(row_end.saturating_sub(row_start + 1));
+        self.scroll_to(row_start + row_offset);
+    }
+
+    fn heading_titles(&self) -> Vec<(usize, String)> {
+        self.headings
+            .iter()
+            .filter_map(|heading| {
+                let line = self.content.get(heading.line)?;
+                Some((heading.line, search::line_text(line).trim().to_string()))
+            })
+            .collect()
     }
 
     fn apply_render(&mut self, render: RenderedMarkdown) {
+        // Re-rendering the same document (for a new width, say) keeps the
+        // selection on the same source line.
+        let selection = self
+            .selection
+            .map(|line| anchor::capture(&self.source_lines, &self.heading_titles(), line, 0));
+        let mut line_sources = render.line_sources;
+        if render.lines.is_empty() {
+            line_sources.push(0..0);
+        }
+        self.source_lines = anchor::source_line_numbers(&self.source, &line_sources);
+        self.line_sources = line_sources;
         self.content = ensure_non_empty(render.lines);
         self.headings = render.headings;
         self.code_blocks = render.code_blocks;
+        self.tables = render.tables;
         self.rules = render.rules;
+        self.links = render.links;
+        self.images = render.images;
+        self.focused_link = None;
+        self.search.refresh(&self.content);
+        self.selection = selection.map(|selection| {
+            anchor::resolve(&selection, &self.source_lines, &self.heading_titles()).line
+        });
     }
 
     fn highlight_headings(&self, frame: &mut Frame<'_>, inner: Rect, metrics: &LineMetrics) {
@@ -226,7 +1814,7 @@ impl App {
             }
             let paint_start = row_start.max(visible_start_row) - visible_start_row;
             let paint_end = row_end.min(visible_end_row) - visible_start_row;
-            let (bg, _) = heading_block_colors(heading.level);
+            let (bg, _) = self.theme.heading_band(heading.level);
             for offset in paint_start..paint_end {
                 if offset >= inner.height as usize {
                     break;
@@ -234,7 +1822,10 @@ impl App {
                 let y = inner.y + offset as u16;
                 let x_end = inner.x.saturating_add(inner.width);
                 for x in inner.x..x_end {
-                    buf.get_mut(x, y).set_bg(bg);
+                    let cell = buf.get_mut(x, y);
+                    if cell.bg == Color::Reset {
+                        cell.set_bg(bg);
+                    }
                 }
             }
         }
@@ -247,7 +1838,7 @@ impl App {
         let visible_start_row = self.scroll;
         let visible_end_row = visible_start_row + inner.height as usize;
         let buf = frame.buffer_mut();
-        let style = Style::default().fg(Color::DarkGray);
+        let style = Style::default().fg(self.theme.rule);
         for &line_idx in &self.rules {
             if line_idx >= self.content.len() {
                 continue;
@@ -332,12 +1923,12 @@ impl App {
                 width: inner.width,
                 height: area_height,
             };
-            Self::draw_code_block_border(frame.buffer_mut(), area, block.language.as_deref());
-            Self::fill_code_block_background(frame.buffer_mut(), area, inner);
+            self.draw_code_block_border(frame.buffer_mut(), area, block.language.as_deref());
+            self.fill_code_block_background(frame.buffer_mut(), area, inner);
         }
     }
 
-    fn draw_code_block_border(buf: &mut Buffer, area: Rect, title: Option<&str>) {
+    fn draw_code_block_border(&self, buf: &mut Buffer, area: Rect, title: Option<&str>) {
         if area.width < 3 || area.height < 3 {
             return;
         }
@@ -345,7 +1936,9 @@ impl App {
         let right = area.x + area.width.saturating_sub(1);
         let top = area.y;
         let bottom = area.y + area.height.saturating_sub(1);
-        let border_style = Style::default().fg(CODE_BLOCK_BORDER_FG).bg(CODE_BLOCK_BG);
+        let border_style = Style::default()
+            .fg(self.theme.code_border)
+            .bg(self.theme.code_bg);
         buf.get_mut(left, top)
             .set_symbol("┌")
             .set_style(border_style);
@@ -381,18 +1974,21 @@ impl App {
         }
     }
 
-    fn fill_code_block_background(buf: &mut Buffer, area: Rect, inner: Rect) {
+    fn fill_code_block_background(&self, buf: &mut Buffer, area: Rect, inner: Rect) {
         if area.height <= 2 {
             return;
         }
-        let inner_style = Style::default().bg(CODE_BLOCK_BG);
+        let inner_style = Style::default().bg(self.theme.code_bg);
         let start_y = area.y.saturating_add(1);
         let end_y = area.y + area.height.saturating_sub(1);
         let start_x = inner.x;
         let end_x = inner.x + inner.width;
         for y in start_y..end_y {
             for x in start_x..end_x {
-                buf.get_mut(x, y).set_style(inner_style);
+                let cell = buf.get_mut(x, y);
+                if cell.bg == Color::Reset {
+                    cell.set_style(inner_style);
+                }
             }
         }
     }
@@ -402,24 +1998,52 @@ impl App {
         frame.render_widget(Clear, popup);
 
         let block = Block::default()
-            .title("Help (? / Esc to close)")
+            .title(format!(
+                "Help ({} / {} to close)",
+                self.keymap.key_for(Action::ToggleHelp),
+                self.keymap.key_for(Action::Cancel)
+            ))
             .borders(Borders::ALL)
-            .style(Style::default().bg(Color::Black));
+            .style(self.theme.ui.popup);
 
         let mut lines = Vec::new();
         let header_style = Style::default()
-            .fg(Color::Cyan)
+            .fg(self.theme.ui.accent)
             .add_modifier(Modifier::BOLD);
         let bullet = |text: &str| Line::from(format!("  • {text}"));
 
-        lines.push(Line::from(Span::styled("Navigation", header_style)));
-        lines.push(bullet("Space / n: page down"));
-        lines.push(bullet("p: page up"));
-        lines.push(bullet("j / k or arrow keys: line scroll"));
-        lines.push(bullet("PgUp / PgDn: page scroll"));
-        lines.push(bullet("g or Home: top  |  G or End: bottom"));
-        lines.push(bullet("r: reload file  |  q or Ctrl+C: quit"));
-        lines.push(bullet("?: toggle this help overlay"));
+        let mut section = "";
+        for info in ACTIONS {
+            if info.section != section {
+                if !section.is_empty() {
+                    lines.push(Line::from(""));
+                }
+                section = info.section;
+                lines.push(Line::from(Span::styled(section, header_style)));
+            }
+            let keys = self.keymap.keys_for(info.action);
+            let keys = if keys.is_empty() {
+                String::from("(unbound)")
+            } else {
+                keys.join(" / ")
+            };
+            lines.push(bullet(&format!("{keys}: {}", info.help)));
+        }
+        lines.push(bullet(
+            "Counts: a number before a key repeats it (10j, 3n); 50% and 120G take it as a target",
+        ));
+        lines.push(bullet(
+            "Outline: movement keys select, open jumps, cancel returns to the document",
+        ));
+        lines.push(bullet(
+            "Zoom: movement keys and scroll-left / scroll-right move around, open or cancel closes",
+        ));
+        lines.push(bullet(
+            "Select: movement keys extend the selection, yank copies it, cancel drops it",
+        ));
+        lines.push(bullet(
+            "Mouse: wheel scrolls, click links / outline, drag the right edge",
+        ));
         lines.push(Line::from(""));
 
         lines.push(Line::from(Span::styled("Heading Styles", header_style)));
@@ -433,11 +2057,21 @@ impl App {
         lines.push(Line::from(""));
 
         lines.push(Line::from(Span::styled("Tips", header_style)));
+        let key = |action| self.keymap.key_for(action);
+        lines.push(bullet(&format!(
+            "Edit in another window, press {} to refresh or {} to reload on save.",
+            key(Action::Reload),
+            key(Action::ToggleWatch)
+        )));
+        lines.push(bullet(&format!(
+            "Use {} to skim; {}/{} jump to top/bottom.",
+            key(Action::PageDown),
+            key(Action::Top),
+            key(Action::Bottom)
+        )));
         lines.push(bullet(
-            "Edit in another window, press r to refresh instantly.",
+            "Keys can be rebound in the [keys] table of config.toml.",
         ));
-        lines.push(bullet("Use Space/PgDn to skim; g/G jump to top/bottom."));
-        lines.push(bullet("Arrow keys still work for fine-grained scrolling."));
 
         let paragraph = Paragraph::new(lines)
             .wrap(Wrap { trim: false })
@@ -445,35 +2079,188 @@ impl App {
         frame.render_widget(paragraph, popup);
     }
 
+    fn render_images(&mut self, frame: &mut Frame<'_>, inner: Rect, metrics: &LineMetrics) {
+        self.graphics.begin_frame();
+        // Kitty and sixel images sit above the text, so they would cover
+        // the help and zoom popups.
+        let popup = self.show_help || self.zoom.is_some();
+        if popup && self.graphics.protocol() != Some(Protocol::HalfBlocks) {
+            return;
+        }
+        let visible = self.scroll..self.scroll + inner.height as usize;
+        for image in &self.images {
+            let Some((start, end)) = metrics.line_range(image.line, image.line + image.rows) else {
+                continue;
+            };
+            let top = start.max(visible.start);
+            let bottom = end.min(visible.end);
+            if top >= bottom {
+                continue;
+            }
+            let offset = image.offset.min(inner.width as usize);
+            let placement = Placement {
+                path: image.path.clone(),
+                x: inner.x + offset as u16,
+                y: inner.y + (top - self.scroll) as u16,
+                cols: image.cols.min(inner.width as usize - offset),
+                rows: image.rows,
+                first_row: top - start,
+                visible_rows: bottom - top,
+            };
+            self.graphics.place(frame.buffer_mut(), placement);
+        }
+    }
+
+    /// Reverses every row of the selected lines, wrapped rows included.
+    fn render_selection(&self, frame: &mut Frame<'_>, inner: Rect, metrics: &LineMetrics) {
+        if self.selection.is_none() {
+            return;
+        }
+        let lines = self.selected_lines();
+        let Some((start, end)) = metrics.line_range(lines.start, lines.end) else {
+            return;
+        };
+        let top = start.max(self.scroll);
+        let bottom = end.min(self.scroll + inner.height as usize);
+        let buf = frame.buffer_mut();
+        for row in top..bottom {
+            let y = inner.y + (row - self.scroll) as u16;
+            for x in inner.x..inner.x.saturating_add(inner.width) {
+                buf.get_mut(x, y)
+                    .set_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+        }
+    }
+
+    fn render_cursor(&self, frame: &mut Frame<'_>, inner: Rect) {
+        let row = self.cursor_row() as u16;
+        if inner.x == 0 || row >= inner.height {
+            return;
+        }
+        let cell = frame.buffer_mut().get_mut(inner.x - 1, inner.y + row);
+        cell.set_symbol("▸");
+        cell.set_style(Style::default().fg(self.theme.list_marker));
+    }
+
+    fn render_scrollbar(&self, frame: &mut Frame<'_>, viewport: Rect) {
+        let max_scroll = self.max_scroll();
+        if max_scroll == 0 {
+            return;
+        }
+        let area = viewport.inner(&Margin {
+            vertical: 1,
+            horizontal: 0,
+        });
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_symbol(Some("│"))
+            .track_style(Style::default().fg(self.theme.ui.muted))
+            .thumb_style(Style::default().fg(self.theme.ui.accent));
+        let mut state = ScrollbarState::new(max_scroll)
+            .position(self.scroll)
+            .viewport_content_length(self.viewport_height as usize);
+        frame.render_stateful_widget(scrollbar, area, &mut state);
+    }
+
+    /// Reading position such as "42% · §Installation" for the title bar.
+    fn progress_line(&self) -> Line<'static> {
+        let max_scroll = self.max_scroll();
+        let percent = (self.scroll.min(max_scroll) * 100)
+            .checked_div(max_scroll)
+            .unwrap_or(100);
+        let mut text = format!("{percent}%");
+        let top_line = self.line_at_row(self.scroll);
+        if let Some((_, title)) = self
+            .heading_titles()
+            .into_iter()
+            .rev()
+            .find(|(line, _)| *line <= top_line)
+        {
+            text.push_str(&format!(" · §{title}"));
+        }
+        Line::from(Span::styled(
+            text,
+            Style::default().fg(self.theme.ui.secondary),
+        ))
+    }
+
     fn title_line(&self) -> Line<'static> {
-        Line::from(vec![
+        let mut spans = vec![
             Span::styled(
-                format!("{}", self.path.display()),
-                Style::default().fg(Color::Cyan),
+                if self.is_stdin() {
+                    String::from("<stdin>")
+                } else {
+                    format!("{}", self.path.display())
+                },
+                Style::default().fg(self.theme.ui.accent),
             ),
             Span::raw(" "),
             Span::styled(
                 format!("({} lines)", self.content.len()),
-                Style::default().fg(Color::Gray),
+                Style::default().fg(self.theme.ui.secondary),
             ),
-        ])
+        ];
+        let (back, forward) = (self.history.len_back(), self.history.len_forward());
+        if back + forward > 0 {
+            spans.push(Span::styled(
+                format!(" [{back} back · {forward} forward]"),
+                Style::default().fg(self.theme.ui.muted),
+            ));
+        }
+        Line::from(spans)
     }
 
     fn status_line(&self) -> Line<'static> {
-        let mut spans = vec![Span::raw(
-            "Space or n: page ↓  p: page ↑  j/k: line  g/G: top/end  r: reload  q: quit",
-        )];
+        let hint = STATUS_HINTS
+            .iter()
+            .map(|(actions, label)| {
+                let keys: Vec<String> = actions
+                    .iter()
+                    .map(|action| self.keymap.key_for(*action))
+                    .collect();
+                format!("{}: {label}", keys.join("/"))
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        let mut spans = vec![Span::raw(hint)];
+        let mut pending = self.keymap.pending_keys();
+        if let Some(action) = self.mark_prompt {
+            pending.push_str(&self.keymap.key_for(action));
+        }
+        if !pending.is_empty() {
+            spans.push(Span::styled(
+                format!("  {pending}"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
+        if self.selection.is_some() {
+            let count = self.selected_lines().len();
+            let plural = if count == 1 { "" } else { "s" };
+            spans.push(Span::styled(
+                format!("  SELECT {count} line{plural}"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
         if let Some(status) = &self.status {
             spans.push(Span::raw("  -  "));
             spans.push(Span::styled(
                 status.clone(),
-                Style::default().fg(Color::Yellow),
+                Style::default().fg(self.theme.ui.highlight),
             ));
         }
         Line::from(spans)
     }
 }
 
+fn greeting(keymap: &Keymap) -> String {
+    format!(
+        "Press {} for help, {} to quit",
+        keymap.key_for(Action::ToggleHelp),
+        keymap.key_for(Action::Quit)
+    )
+}
+
 fn ensure_non_empty(mut lines: Vec<Line<'static>>) -> Vec<Line<'static>> {
     if lines.is_empty() {
         lines.push(Line::from("(file is empty)"));
@@ -481,6 +2268,141 @@ fn ensure_non_empty(mut lines: Vec<Line<'static>>) -> Vec<Line<'static>> {
     lines
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        if is_stdin(path) {
+            return None;
+        }
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Reads markdown from `path`, or from standard input when the path is `-`.
+///
+/// Keys keep working after stdin is used up because crossterm reads events
+/// from `/dev/tty`, not stdin, whenever stdin is not a terminal. Never read
+/// events from stdin itself.
+pub fn read_markdown(path: &Path) -> io::Result<String> {
+    if is_stdin(path) {
+        let mut markdown = String::new();
+        io::stdin().read_to_string(&mut markdown)?;
+        Ok(markdown)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// A table or code block opened full-screen, scrolled on its own.
+struct Zoom {
+    title: String,
+    lines: Vec<Line<'static>>,
+    /// The code as written, when a code block is zoomed.
+    code: Option<String>,
+    scroll: usize,
+    column: usize,
+    /// Width and height of the popup's text area at the last draw.
+    view: (usize, usize),
+}
+
+impl Zoom {
+    fn new(title: &str, lines: Vec<Line<'static>>, code: Option<String>) -> Self {
+        Self {
+            title: title.to_string(),
+            lines,
+            code,
+            scroll: 0,
+            column: 0,
+            view: (1, 1),
+        }
+    }
+
+    fn clamp(&mut self) {
+        let (width, height) = self.view;
+        let widest = self.lines.iter().map(Line::width).max().unwrap_or(0);
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(height));
+        self.column = self.column.min(widest.saturating_sub(width));
+    }
+
+    fn render(&mut self, frame: &mut Frame<'_>, area: Rect, theme: &Theme, keymap: &Keymap) {
+        let popup = centered_rect(96, 92, area);
+        frame.render_widget(Clear, popup);
+        let background = if self.code.is_some() {
+            theme.code_bg
+        } else {
+            Color::Reset
+        };
+        let block = Block::default()
+            .title(format!(
+                " {} ({} / {} to close) ",
+                self.title,
+                keymap.key_for(Action::Open),
+                keymap.key_for(Action::Cancel)
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.code_border))
+            .style(Style::default().bg(background));
+        let inner = block.inner(popup).inner(&Margin {
+            horizontal: 1,
+            vertical: 0,
+        });
+        frame.render_widget(block, popup);
+        self.view = (inner.width as usize, inner.height as usize);
+        self.clamp();
+        let paragraph = Paragraph::new(self.lines.clone()).scroll((
+            self.scroll.min(u16::MAX as usize) as u16,
+            self.column.min(u16::MAX as usize) as u16,
+        ));
+        frame.render_widget(paragraph, inner);
+    }
+}
+
+/// A table or code block, by its position among blocks of its kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum BlockId {
+    Table(usize),
+    Code(usize),
+}
+
+/// The markdown `block` was rendered from.
+fn block_text<'a>(
+    source: &'a str,
+    tables: &[TableOverlay],
+    code_blocks: &[CodeBlockOverlay],
+    line_sources: &[Range<usize>],
+    block: BlockId,
+) -> Option<&'a str> {
+    let range = match block {
+        BlockId::Table(idx) => line_sources.get(tables.get(idx)?.line_start)?.clone(),
+        BlockId::Code(idx) => code_blocks.get(idx)?.source_range.clone(),
+    };
+    source.get(range)
+}
+
+#[derive(Default)]
+struct ViewLayout {
+    outline: Option<Rect>,
+    viewer: Rect,
+    inner: Rect,
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
 struct LineMetrics {
     offsets: Vec<usize>,
 }
@@ -494,6 +2416,12 @@ impl LineMetrics {
         let end = *self.offsets.get(end_line)?;
         Some((start, end))
     }
+
+    fn line_at_row(&self, row: usize) -> usize {
+        let idx = self.offsets.partition_point(|&offset| offset <= row);
+        idx.saturating_sub(1)
+            .min(self.offsets.len().saturating_sub(2))
+    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
@@ -521,3 +2449,254 @@ fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
         .split(vertical[1]);
     horizontal[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_for(markdown: &str, height: u16) -> App {
+        let options = MarkdownOptions::default();
+        let render = markdown_to_render_with_options(markdown, options.clone());
+        let mut app = App::new(
+            PathBuf::from("test.md"),
+            markdown.to_string(),
+            render,
+            options,
+        );
+        app.viewport_width = 40;
+        app.viewport_height = height;
+        app
+    }
+
+    fn heading_row(app: &App, title: &str) -> usize {
+        let (line, _) = app
+            .heading_titles()
+            .into_iter()
+            .find(|(_, text)| text == title)
+            .unwrap();
+        let metrics = app.compute_line_metrics(app.viewport_width as usize);
+        metrics.line_range(line, line + 1).unwrap().0
+    }
+
+    #[test]
+    fn heading_jumps_reach_a_heading_on_the_last_page() {
+        let filler: String = (0..20).map(|i| format!("line {i}\n\n")).collect();
+        let markdown = format!("# One\n\n{filler}# Two\n\n{filler}# Three\n\nend\n");
+        let mut app = app_for(&markdown, 10);
+        let two = heading_row(&app, "Two");
+        assert!(heading_row(&app, "Three") > app.max_scroll());
+
+        app.next_heading(HeadingLevel::H6);
+        assert_eq!(app.scroll, two);
+        app.next_heading(HeadingLevel::H6);
+        assert_eq!(app.scroll, app.max_scroll());
+        app.status = None;
+        app.next_heading(HeadingLevel::H6);
+        assert_eq!(app.scroll, app.max_scroll());
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Last heading is already in view")
+        );
+
+        app.prev_heading(HeadingLevel::H6);
+        assert_eq!(app.scroll, two);
+        app.prev_heading(HeadingLevel::H6);
+        assert_eq!(app.scroll, 0);
+        app.prev_heading(HeadingLevel::H6);
+        assert_eq!(app.status.as_deref(), Some("No more headings above"));
+    }
+
+    #[test]
+    fn clicks_follow_the_word_wrapped_layout() {
+        let mut app = app_for("aaaaaaaaaaaaaaaa [link](x.md) more\n", 10);
+        assert_eq!(app.link_at(0, 1, 2, 20), Some(0));
+        assert_eq!(app.link_at(0, 1, 6, 20), None);
+        assert_eq!(app.link_at(0, 0, 18, 20), None);
+        app.viewport_width = 40;
+        assert_eq!(app.link_at(0, 0, 18, 40), Some(0));
+
+        let app = app_for("漢字漢字 [漢字](x.md)\n", 10);
+        assert_eq!(app.link_at(0, 0, 9, 10), None);
+        assert_eq!(app.link_at(0, 1, 0, 10), Some(0));
+        assert_eq!(app.link_at(0, 1, 3, 10), Some(0));
+        assert_eq!(app.link_at(0, 1, 4, 10), None);
+    }
+
+    #[test]
+    fn counted_g_reaches_lines_past_the_repeat_limit() {
+        let code: String = (0..12_005).map(|i| format!("{i}\n")).collect();
+        let mut app = app_for(&format!("```\n{code}```\n"), 10);
+        for ch in "12000G".chars() {
+            let key = KeyEvent::new(KeyCode::Char(ch), crossterm::event::KeyModifiers::NONE);
+            crate::handle_key(&mut app, key).unwrap();
+        }
+        assert_eq!(app.source_lines[app.cursor_line()], 11_999);
+    }
+
+    #[test]
+    fn scroll_helpers_move_the_view_and_cursor() {
+        let markdown: String = (1..=40).map(|i| format!("line {i}\n\n")).collect();
+        let mut app = app_for(&markdown, 10);
+        let row_text = |app: &App| search::line_text(&app.content[app.cursor_line()]);
+
+        app.screen_bottom(1);
+        assert_eq!(app.cursor_row(), 9);
+        app.screen_middle();
+        assert_eq!(app.cursor_row(), 4);
+        app.screen_top(3);
+        assert_eq!(app.cursor_row(), 2);
+
+        let before = row_text(&app);
+        app.view_down(2);
+        assert_eq!((app.scroll, app.cursor_row()), (2, 0));
+        assert_eq!(row_text(&app), before);
+        app.view_up(2);
+        assert_eq!((app.scroll, app.cursor_row()), (0, 2));
+
+        app.goto_source_line(21);
+        assert_eq!(row_text(&app), "line 11");
+        app.scroll_to_percent(50);
+        assert_eq!(app.scroll, app.max_scroll().div_ceil(2));
+        app.scroll_to_percent(250);
+        assert_eq!(app.scroll, app.max_scroll());
+    }
+
+    #[test]
+    fn jumps_that_do_not_move_are_not_recorded() {
+        let markdown: String = (1..=40).map(|i| format!("line {i}\n\n")).collect();
+        let mut app = app_for(&markdown, 10);
+        let press = |app: &mut App, code: KeyCode, modifiers| {
+            crate::handle_key(app, KeyEvent::new(code, modifiers)).unwrap();
+        };
+        let none = crossterm::event::KeyModifiers::NONE;
+        let ctrl = crossterm::event::KeyModifiers::CONTROL;
+        press(&mut app, KeyCode::Char('G'), none);
+        press(&mut app, KeyCode::Char('G'), none);
+        press(&mut app, KeyCode::Char('n'), none);
+        assert_eq!(app.scroll, app.max_scroll());
+        press(&mut app, KeyCode::Char('o'), ctrl);
+        assert_eq!(app.scroll, 0);
+        press(&mut app, KeyCode::Char('o'), ctrl);
+        assert_eq!(app.scroll, 0);
+        assert_eq!(app.status.as_deref(), Some("Already at the oldest jump"));
+    }
+
+    fn line_of(app: &App, needle: &str) -> usize {
+        app.content
+            .iter()
+            .position(|line| search::line_text(line).contains(needle))
+            .unwrap()
+    }
+
+    /// Selects from content line `from` and puts the cursor on `row` of line `to`.
+    fn select(app: &mut App, from: usize, to: usize, row: usize) {
+        let metrics = app.compute_line_metrics(app.viewport_width as usize);
+        app.scroll = 0;
+        app.cursor_offset = metrics.line_range(to, to + 1).unwrap().0 + row;
+        app.selection = Some(from);
+    }
+
+    #[test]
+    fn selections_map_back_to_their_markdown() {
+        let markdown = "intro\n\n```rust\nlet a = 1;\nlet b = 2;\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n| 3 | 4 |\n\nlast line";
+        let mut app = app_for(markdown, 40);
+        let block = &app.code_blocks[0];
+        let (top, bottom) = (block.line_start - 1, block.line_end);
+        let fenced = "```rust\nlet a = 1;\nlet b = 2;\n```\n";
+
+        select(&mut app, top, top, 0);
+        assert_eq!(app.selected_lines(), top..top + 1);
+        assert_eq!(app.source_for_lines(app.selected_lines()), fenced);
+        let code = line_of(&app, "let a");
+        select(&mut app, top, code, 0);
+        assert_eq!(app.source_for_lines(app.selected_lines()), fenced);
+        select(&mut app, bottom, bottom, 0);
+        assert_eq!(app.source_for_lines(app.selected_lines()), fenced);
+
+        let (one, three) = (line_of(&app, "│ 1"), line_of(&app, "│ 3"));
+        select(&mut app, three, one, 0);
+        assert_eq!(app.selected_lines(), one..three + 1);
+        assert_eq!(
+            app.source_for_lines(app.selected_lines()),
+            "| a | b |\n|---|---|\n| 1 | 2 |\n| 3 | 4 |\n"
+        );
+
+        let last = app.content.len() - 1;
+        select(&mut app, last, last, 0);
+        assert_eq!(app.source_for_lines(app.selected_lines()), "last line");
+    }
+
+    #[test]
+    fn yanking_wrapped_lines_copies_each_line_once() {
+        let path = std::env::temp_dir().join(format!("md-viewer-select-{}", std::process::id()));
+        let long = "alpha beta gamma delta epsilon zeta eta theta iota kappa lambda";
+        let mut app = app_for(&format!("{long}\n\nshort\n"), 40);
+        app.set_clipboard(Clipboard::File(path.clone()));
+        let metrics = app.compute_line_metrics(app.viewport_width as usize);
+        assert_eq!(metrics.line_range(0, 1), Some((0, 2)));
+
+        select(&mut app, 0, 0, 1);
+        assert_eq!(app.selected_lines(), 0..1);
+        app.yank(false);
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{long}\n"));
+
+        let short = line_of(&app, "short");
+        select(&mut app, short, 0, 1);
+        app.yank(false);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{long}\n\nshort\n")
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn opened_documents_return_to_their_saved_position() {
+        let dir = std::env::temp_dir().join(format!("md-viewer-open-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let long: String = (1..=40).map(|i| format!("line {i}\n\n")).collect();
+        fs::write(dir.join("a.md"), "[b](b.md)\n").unwrap();
+        fs::write(dir.join("b.md"), &long).unwrap();
+        let mut app = app_for("[b](b.md)\n", 10);
+        app.path = dir.join("a.md");
+        app.positions = Positions::in_memory();
+
+        app.open_document(&dir.join("b.md")).unwrap();
+        app.scroll_to(20);
+        app.go_back();
+        assert_eq!(app.scroll, 0);
+        app.open_document(&dir.join("b.md")).unwrap();
+        assert_eq!(app.scroll, 20);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unwrapped_blocks_follow_their_markdown_across_reloads() {
+        let path = std::env::temp_dir().join(format!("md-viewer-reload-{}.md", std::process::id()));
+        let table = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let code = "```\nlet x = 1;\n```\n";
+        let markdown = format!("{table}\n{code}");
+        fs::write(&path, &markdown).unwrap();
+        let mut app = app_for(&markdown, 10);
+        app.path = path.clone();
+        app.no_wrap.insert(BlockId::Table(0), 0);
+        app.no_wrap.insert(BlockId::Code(0), 3);
+
+        let edited = format!("| new |\n|---|\n| x |\n\n```\nnew\n```\n\n{table}\n{code}");
+        fs::write(&path, edited).unwrap();
+        app.reload().unwrap();
+        assert_eq!(
+            app.no_wrap,
+            HashMap::from([(BlockId::Table(1), 0), (BlockId::Code(1), 3)])
+        );
+        assert_eq!(
+            app.markdown_options().no_wrap_tables,
+            std::collections::HashSet::from([1])
+        );
+
+        fs::write(&path, "| a | b |\n|---|---|\n| 1 | 3 |\n").unwrap();
+        app.reload().unwrap();
+        assert!(app.no_wrap.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+/// Where yanked text goes. OSC 52 asks the terminal itself to set the system
+/// clipboard, which also works over SSH; the others are fallbacks for
+/// terminals that ignore it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Clipboard {
+    #[default]
+    Osc52,
+    /// A shell command that reads the text on stdin, e.g. `wl-copy`.
+    Command(String),
+    File(PathBuf),
+}
+
+/// What is left to do after `Clipboard::copy` returns.
+pub enum Copied {
+    Done,
+    /// An escape sequence still to be written to the terminal.
+    Escape(String),
+    /// A command still running; its outcome arrives on the receiver.
+    Running(Receiver<io::Result<()>>),
+}
+
+impl Clipboard {
+    /// `osc52`, `file:PATH`, or anything else as a command line.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(String::from(
+                "Empty clipboard setting (expected osc52, file:PATH or a command)",
+            ));
+        }
+        if value.eq_ignore_ascii_case("osc52") {
+            return Ok(Clipboard::Osc52);
+        }
+        if let Some(path) = value.strip_prefix("file:") {
+            return Ok(Clipboard::File(expand_home(path.trim())));
+        }
+        Ok(Clipboard::Command(value.to_string()))
+    }
+
+    /// Copies `text`. Commands are fed on a background thread so a slow
+    /// one never stalls the viewer.
+    pub fn copy(&self, text: &str) -> io::Result<Copied> {
+        match self {
+            Clipboard::Osc52 => Ok(Copied::Escape(osc52(text, env::var_os("TMUX").is_some()))),
+            Clipboard::Command(command) => spawn(command, text).map(Copied::Running),
+            Clipboard::File(path) => fs::write(path, text).map(|_| Copied::Done),
+        }
+    }
+
+    /// Where the text went, for the status line.
+    pub fn target(&self) -> String {
+        match self {
+            Clipboard::Osc52 => String::from("the clipboard"),
+            Clipboard::Command(command) => format!("`{command}`"),
+            Clipboard::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if tmux {
+        // tmux only forwards escapes wrapped in a passthrough with ESC doubled.
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+fn spawn(command: &str, text: &str) -> io::Result<Receiver<io::Result<()>>> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdin = child.stdin.take();
+    let (command, text) = (command.to_string(), text.to_string());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(feed(child, stdin, &command, &text));
+    });
+    Ok(receiver)
+}
+
+fn feed(mut child: Child, stdin: Option<ChildStdin>, command: &str, text: &str) -> io::Result<()> {
+    // Dropping stdin closes it, so the command sees the end of input. A
+    // command that quits before reading everything is still waited for.
+    let written = stdin.map_or(Ok(()), |mut stdin| stdin.write_all(text.as_bytes()));
+    let status = child.wait();
+    written?;
+    let status = status?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "`{command}` exited with {status}"
+        )))
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_targets_and_encodes_osc52() {
+        assert_eq!(Clipboard::parse("OSC52"), Ok(Clipboard::Osc52));
+        assert_eq!(
+            Clipboard::parse("file:/tmp/yank.txt"),
+            Ok(Clipboard::File(PathBuf::from("/tmp/yank.txt")))
+        );
+        assert_eq!(
+            Clipboard::parse(" wl-copy "),
+            Ok(Clipboard::Command(String::from("wl-copy")))
+        );
+        assert!(Clipboard::parse("").is_err());
+        assert_eq!(osc52("hi\n", false), "\x1b]52;c;aGkK\x07");
+        assert_eq!(
+            osc52("hi\n", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGkK\x07\x1b\\"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn commands_run_in_the_background() {
+        let path = env::temp_dir().join(format!("md-viewer-yank-{}", std::process::id()));
+        let clipboard = Clipboard::Command(format!("cat > '{}'", path.display()));
+        let Ok(Copied::Running(done)) = clipboard.copy("yanked\n") else {
+            panic!("expected a running command");
+        };
+        done.recv().unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "yanked\n");
+        fs::remove_file(&path).unwrap();
+
+        let Ok(Copied::Running(done)) = Clipboard::Command(String::from("exit 3")).copy("") else {
+            panic!("expected a running command");
+        };
+        assert!(done.recv().unwrap().is_err());
+
+        // Quits without reading a large input; the write fails but the
+        // command is still reaped.
+        let Ok(Copied::Running(done)) =
+            Clipboard::Command(String::from("exit 0")).copy(&"x".repeat(1 << 20))
+        else {
+            panic!("expected a running command");
+        };
+        let err = done.recv().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
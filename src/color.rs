@@ -0,0 +1,180 @@
+use std::env;
+
+use ratatui::{buffer::Buffer, style::Color};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    Never,
+}
+
+pub const COLOR_DEPTH_NAMES: [&str; 5] = ["auto", "truecolor", "256", "16", "never"];
+
+/// xterm's default RGB values for the 16 named colors, in index order.
+const ANSI_PALETTE: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorDepth {
+    /// Parses a `--color` value; `auto` yields `None` so the caller can detect.
+    pub fn parse(name: &str) -> Result<Option<ColorDepth>, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(None),
+            "truecolor" | "24bit" | "24-bit" => Ok(Some(ColorDepth::TrueColor)),
+            "256" => Ok(Some(ColorDepth::Ansi256)),
+            "16" => Ok(Some(ColorDepth::Ansi16)),
+            "never" | "none" | "off" => Ok(Some(ColorDepth::Never)),
+            other => Err(format!(
+                "Unknown color mode '{other}' (available: {})",
+                COLOR_DEPTH_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Guesses the terminal's color support from `NO_COLOR`, `COLORTERM` and `TERM`.
+    pub fn detect() -> ColorDepth {
+        let var = |name: &str| env::var(name).unwrap_or_default().to_ascii_lowercase();
+        if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return ColorDepth::Never;
+        }
+        let colorterm = var("COLORTERM");
+        if colorterm.contains("truecolor") || colorterm.contains("24bit") {
+            return ColorDepth::TrueColor;
+        }
+        let term = var("TERM");
+        if term == "dumb" {
+            ColorDepth::Never
+        } else if term.contains("direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256") {
+            ColorDepth::Ansi256
+        } else if term.is_empty() && cfg!(windows) {
+            ColorDepth::TrueColor
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    pub fn downsample(self, color: Color) -> Color {
+        match (self, color) {
+            (_, Color::Reset) | (ColorDepth::TrueColor, _) => color,
+            (ColorDepth::Never, _) => Color::Reset,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256(r, g, b)),
+            (ColorDepth::Ansi256, _) => color,
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => nearest_16((r, g, b)),
+            (ColorDepth::Ansi16, Color::Indexed(idx)) if idx < 16 => ANSI_PALETTE[idx as usize].0,
+            (ColorDepth::Ansi16, Color::Indexed(idx)) => nearest_16(indexed_rgb(idx)),
+            (ColorDepth::Ansi16, _) => color,
+        }
+    }
+
+    /// Rewrites every cell of a drawn frame to colors the terminal can show.
+    pub fn downsample_buffer(self, buf: &mut Buffer) {
+        if self == ColorDepth::TrueColor {
+            return;
+        }
+        for cell in &mut buf.content {
+            cell.fg = self.downsample(cell.fg);
+            cell.bg = self.downsample(cell.bg);
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).unsigned_abs();
+    let (dr, dg, db) = (d(a.0, b.0), d(a.1, b.1), d(a.2, b.2));
+    2 * dr * dr + 4 * dg * dg + 3 * db * db
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    ANSI_PALETTE
+        .iter()
+        .min_by_key(|(_, candidate)| distance(rgb, *candidate))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |value: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (**level as i32 - value as i32).abs())
+            .map(|(idx, _)| idx as u8)
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = 16 + 36 * ri + 6 * gi + bi;
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 232 + gray_step;
+    if distance((r, g, b), indexed_rgb(gray)) < distance((r, g, b), indexed_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn indexed_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => ANSI_PALETTE[idx as usize].1,
+        16..=231 => {
+            let idx = idx - 16;
+            (
+                CUBE_LEVELS[(idx / 36) as usize],
+                CUBE_LEVELS[(idx / 6 % 6) as usize],
+                CUBE_LEVELS[(idx % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (idx - 232);
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_maps_onto_cube_and_gray_ramp() {
+        let depth = ColorDepth::Ansi256;
+        assert_eq!(depth.downsample(Color::Rgb(255, 0, 0)), Color::Indexed(196));
+        assert_eq!(
+            depth.downsample(Color::Rgb(48, 48, 48)),
+            Color::Indexed(236)
+        );
+        assert_eq!(depth.downsample(Color::Cyan), Color::Cyan);
+    }
+
+    #[test]
+    fn sixteen_colors_and_never() {
+        let depth = ColorDepth::Ansi16;
+        assert_eq!(depth.downsample(Color::Rgb(12, 16, 26)), Color::Black);
+        assert_eq!(depth.downsample(Color::Rgb(235, 235, 245)), Color::Gray);
+        assert_eq!(depth.downsample(Color::Indexed(9)), Color::LightRed);
+        assert_eq!(ColorDepth::Never.downsample(Color::Cyan), Color::Reset);
+        assert_eq!(ColorDepth::parse("auto"), Ok(None));
+        assert!(ColorDepth::parse("8").is_err());
+    }
+}
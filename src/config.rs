@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::keymap::Keymap;
+
+/// Settings read from `config.toml`. Every field is optional; command-line
+/// flags take precedence over anything set here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub theme: Option<String>,
+    pub color: Option<String>,
+    pub max_table_width: Option<usize>,
+    pub wrap_width: Option<usize>,
+    pub heading_bands: Option<bool>,
+    pub images: Option<String>,
+    /// `osc52`, `file:PATH` or a command that reads the text on stdin.
+    pub clipboard: Option<String>,
+    pub defaults: Defaults,
+    /// Key overrides by action name, e.g. `top = ["gg", "Home"]`.
+    pub keys: BTreeMap<String, Spanned<KeyList>>,
+}
+
+impl Config {
+    /// The `[keys]` table as overrides for `Keymap::with_overrides`.
+    pub fn key_overrides(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.keys
+            .iter()
+            .map(|(action, keys)| (action.as_str(), keys.get_ref().as_slice()))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub watch: bool,
+    pub mouse: bool,
+    pub outline: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            KeyList::One(key) => std::slice::from_ref(key),
+            KeyList::Many(keys) => keys,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{line}:{column}: {message}", path.display()),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/md-viewer/config.toml`, falling back to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("md-viewer").join("config.toml"))
+}
+
+/// Loads `explicit` if given, otherwise the default location. A missing
+/// default file is not an error; a missing explicit one is.
+pub fn load(explicit: Option<&Path>) -> Result<Config, ConfigError> {
+    let (path, required) = match explicit {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&path, &text),
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !required => Ok(Config::default()),
+        Err(err) => Err(ConfigError::Io(path, err)),
+    }
+}
+
+/// Parses the config and checks that its `[keys]` bindings fit together, so
+/// a conflict is reported at the entry that caused it.
+pub fn parse(path: &Path, text: &str) -> Result<Config, ConfigError> {
+    let error = |offset: usize, message: String| {
+        let before = &text[..offset.min(text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rfind('\n')
+            .map(|newline| before[newline + 1..].chars().count())
+            .unwrap_or_else(|| before.chars().count())
+            + 1;
+        ConfigError::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    };
+    let config: Config = toml::from_str(text).map_err(|err| {
+        let offset = err.span().map(|span| span.start).unwrap_or(0);
+        error(offset, err.message().to_string())
+    })?;
+    if let Err(err) = Keymap::with_overrides(config.key_overrides()) {
+        let offset = config
+            .keys
+            .get(&err.action)
+            .map_or(0, |keys| keys.span().start);
+        return Err(error(offset, err.to_string()));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_settings_and_defaults() {
+        let config = parse(
+            Path::new("config.toml"),
+            "theme = \"light\"\nwrap_width = 100\n\n[defaults]\nwatch = true\n\n[keys]\nquit = \"Q\"\ntop = [\"gg\", \"Home\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert_eq!(config.wrap_width, Some(100));
+        assert_eq!(config.heading_bands, None);
+        assert!(config.defaults.watch);
+        assert!(!config.defaults.mouse);
+        assert_eq!(config.keys["quit"].get_ref().as_slice(), ["Q"]);
+        assert_eq!(config.keys["top"].get_ref().as_slice(), ["gg", "Home"]);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let err = parse(
+            Path::new("config.toml"),
+            "theme = \"dark\"\nwrap_width = \"wide\"\n",
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("config.toml:2:14: "), "{message}");
+        let err = parse(Path::new("config.toml"), "\n\ncolour = \"16\"\n").unwrap_err();
+        assert!(err.to_string().starts_with("config.toml:3:1: "), "{err}");
+    }
+
+    #[test]
+    fn conflicting_keys_point_at_their_entry() {
+        let err = parse(
+            Path::new("config.toml"),
+            "[keys]\nreload = \"R\"\nquit = [\"Q\", \"gg\"]\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.toml:3:8: quit: 'gg' for quit can never be typed: 'g' is bound to top"
+        );
+        let err = parse(Path::new("config.toml"), "[keys]\nquit = \"j\"\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.toml:2:8: quit: 'j' is bound to both scroll-down and quit"
+        );
+    }
+}
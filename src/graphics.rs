@@ -0,0 +1,530 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    fmt::Write as _,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::OnceLock,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::{imageops::FilterType, RgbaImage};
+use ratatui::{buffer::Buffer, style::Color};
+
+/// Rows of (upper, lower) pixel colors, one pair per cell.
+pub type HalfBlocks = Vec<Vec<(Color, Color)>>;
+
+/// Tall images are scaled down so they never take more rows than this.
+const MAX_IMAGE_ROWS: usize = 30;
+const KITTY_CHUNK: usize = 4096;
+/// Encoded sixel crops kept around before the cache is reset.
+const SIXEL_CACHE_LIMIT: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+    HalfBlocks,
+}
+
+pub const IMAGE_MODE_NAMES: [&str; 5] = ["auto", "kitty", "sixel", "blocks", "off"];
+
+impl Protocol {
+    /// Parses an `--images` value; `auto` detects and `off` yields `None`.
+    pub fn parse(name: &str) -> Result<Option<Protocol>, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Protocol::detect()),
+            "kitty" => Ok(Some(Protocol::Kitty)),
+            "sixel" => Ok(Some(Protocol::Sixel)),
+            "blocks" | "halfblocks" | "unicode" => Ok(Some(Protocol::HalfBlocks)),
+            "off" | "none" | "never" => Ok(None),
+            other => Err(format!(
+                "Unknown image mode '{other}' (available: {})",
+                IMAGE_MODE_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Picks a graphics protocol from the terminal's environment variables.
+    /// Half blocks are only a fallback someone has to ask for, so terminals
+    /// without kitty or sixel support, and multiplexers that swallow their
+    /// escapes, keep the text labels.
+    pub fn detect() -> Option<Protocol> {
+        let var = |name: &str| env::var(name).unwrap_or_default().to_ascii_lowercase();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if env::var_os("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+            return None;
+        }
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || matches!(program.as_str(), "wezterm" | "ghostty")
+        {
+            Some(Protocol::Kitty)
+        } else if term.starts_with("foot")
+            || term.contains("mlterm")
+            || term.contains("sixel")
+            || program == "iterm.app"
+        {
+            Some(Protocol::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+/// Size of one terminal cell in pixels, falling back to 8x16 when the
+/// terminal does not report its pixel size.
+pub fn cell_pixels() -> (u32, u32) {
+    static CELL: OnceLock<(u32, u32)> = OnceLock::new();
+    *CELL.get_or_init(|| {
+        crossterm::terminal::window_size()
+            .ok()
+            .filter(|size| size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0)
+            .map(|size| {
+                (
+                    (size.width / size.columns).max(1) as u32,
+                    (size.height / size.rows).max(1) as u32,
+                )
+            })
+            .unwrap_or((8, 16))
+    })
+}
+
+/// Pixel sizes of images by path, read from each file once. Clones share
+/// the cache, so documents rendered for `Graphics` reuse what it has seen.
+#[derive(Clone, Default)]
+pub struct ImageSizes(Rc<RefCell<HashMap<PathBuf, Option<PixelSize>>>>);
+
+/// Width and height in pixels.
+pub type PixelSize = (u32, u32);
+
+impl ImageSizes {
+    /// `None` when the file cannot be read as a PNG, JPEG or GIF.
+    pub fn get(&self, path: &Path) -> Option<PixelSize> {
+        *self
+            .0
+            .borrow_mut()
+            .entry(path.to_path_buf())
+            .or_insert_with(|| image::image_dimensions(path).ok())
+    }
+
+    fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+/// Cells an image of `size` pixels occupies when fitted into `max_cols`
+/// columns.
+pub fn fit((width, height): PixelSize, max_cols: usize) -> Option<(usize, usize)> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let (cell_w, cell_h) = cell_pixels();
+    let natural_cols = (width as usize).div_ceil(cell_w as usize);
+    let mut cols = natural_cols.clamp(1, max_cols.max(1));
+    let rows_for = |cols: usize| {
+        let pixel_height = height as f64 * (cols as f64 * cell_w as f64) / width as f64;
+        (pixel_height / cell_h as f64).ceil().max(1.0) as usize
+    };
+    let mut rows = rows_for(cols);
+    if rows > MAX_IMAGE_ROWS {
+        rows = MAX_IMAGE_ROWS;
+        let pixel_width = width as f64 * (rows as f64 * cell_h as f64) / height as f64;
+        cols = ((pixel_width / cell_w as f64).ceil() as usize).clamp(1, cols);
+    }
+    Some((cols, rows))
+}
+
+/// Resolves an image destination against the document's directory; remote
+/// and data URLs are left to the placeholder.
+pub fn local_path(base_dir: &Path, destination: &str) -> Option<PathBuf> {
+    let destination = destination.trim();
+    let is_url = destination
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.len() > 1 && !scheme.contains(['/', '\\']));
+    if destination.is_empty() || is_url {
+        return None;
+    }
+    let path = destination.split(['?', '#']).next().unwrap_or(destination);
+    Some(base_dir.join(path))
+}
+
+fn load_scaled(path: &Path, width: u32, height: u32) -> Option<RgbaImage> {
+    let image = image::open(path).ok()?.to_rgba8();
+    Some(image::imageops::resize(
+        &image,
+        width.max(1),
+        height.max(1),
+        FilterType::Triangle,
+    ))
+}
+
+/// One image cell row as (upper, lower) half-block colors.
+pub fn half_block_rows(path: &Path, cols: usize, rows: usize) -> Option<HalfBlocks> {
+    let image = load_scaled(path, cols as u32, rows as u32 * 2)?;
+    Some(half_blocks(&image))
+}
+
+fn half_blocks(image: &RgbaImage) -> HalfBlocks {
+    let pixel = |x: u32, y: u32| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        if a < 128 {
+            Color::Reset
+        } else {
+            Color::Rgb(r, g, b)
+        }
+    };
+    (0..image.height() / 2)
+        .map(|row| {
+            (0..image.width())
+                .map(|x| (pixel(x, row * 2), pixel(x, row * 2 + 1)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Symbol and colors for one cell; transparent halves keep the terminal
+/// background rather than showing the default foreground.
+pub fn half_block(upper: Color, lower: Color) -> (&'static str, Color, Color) {
+    match (upper, lower) {
+        (Color::Reset, Color::Reset) => (" ", Color::Reset, Color::Reset),
+        (Color::Reset, lower) => ("▄", lower, Color::Reset),
+        (upper, lower) => ("▀", upper, lower),
+    }
+}
+
+/// Where an image sits on screen this frame, and which of its rows show.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub path: PathBuf,
+    pub x: u16,
+    pub y: u16,
+    pub cols: usize,
+    pub rows: usize,
+    pub first_row: usize,
+    pub visible_rows: usize,
+}
+
+/// Decoded images and the terminal state of the graphics drawn so far.
+pub struct Graphics {
+    protocol: Option<Protocol>,
+    sizes: ImageSizes,
+    blocks: HashMap<(PathBuf, usize, usize), Option<HalfBlocks>>,
+    scaled: HashMap<(PathBuf, usize, usize), Option<RgbaImage>>,
+    kitty_ids: HashMap<(PathBuf, usize, usize), u32>,
+    sixels: HashMap<Placement, String>,
+    pending: Vec<Placement>,
+    shown: Vec<Placement>,
+}
+
+impl Graphics {
+    pub fn new(protocol: Option<Protocol>) -> Self {
+        Self {
+            protocol,
+            sizes: ImageSizes::default(),
+            blocks: HashMap::new(),
+            scaled: HashMap::new(),
+            kitty_ids: HashMap::new(),
+            sixels: HashMap::new(),
+            pending: Vec::new(),
+            shown: Vec::new(),
+        }
+    }
+
+    pub fn protocol(&self) -> Option<Protocol> {
+        self.protocol
+    }
+
+    pub fn sizes(&self) -> ImageSizes {
+        self.sizes.clone()
+    }
+
+    /// Forgets decoded images, e.g. after a reload may have changed them.
+    pub fn invalidate(&mut self) {
+        self.sizes.clear();
+        self.blocks.clear();
+        self.scaled.clear();
+        self.sixels.clear();
+        self.kitty_ids.clear();
+        self.shown.clear();
+    }
+
+    /// The terminal was cleared (e.g. on resize), so everything must be
+    /// placed again.
+    pub fn forget_shown(&mut self) {
+        self.shown.clear();
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Draws half blocks straight into the buffer; escape-based protocols
+    /// are queued and written by `flush` once the frame is on screen.
+    pub fn place(&mut self, buf: &mut Buffer, placement: Placement) {
+        match self.protocol {
+            Some(Protocol::HalfBlocks) => self.draw_blocks(buf, &placement),
+            Some(_) => self.pending.push(placement),
+            None => {}
+        }
+    }
+
+    fn draw_blocks(&mut self, buf: &mut Buffer, placement: &Placement) {
+        let key = (placement.path.clone(), placement.cols, placement.rows);
+        let rows = self
+            .blocks
+            .entry(key)
+            .or_insert_with(|| half_block_rows(&placement.path, placement.cols, placement.rows));
+        let Some(rows) = rows else {
+            return;
+        };
+        let area = buf.area;
+        for (dy, row) in rows
+            .iter()
+            .skip(placement.first_row)
+            .take(placement.visible_rows)
+            .enumerate()
+        {
+            let y = placement.y + dy as u16;
+            for (dx, (upper, lower)) in row.iter().enumerate() {
+                let x = placement.x + dx as u16;
+                if x >= area.right() || y >= area.bottom() {
+                    continue;
+                }
+                let (symbol, fg, bg) = half_block(*upper, *lower);
+                let cell = buf.get_mut(x, y);
+                cell.set_symbol(symbol);
+                cell.fg = fg;
+                cell.bg = bg;
+            }
+        }
+    }
+
+    /// Sixel pixels stay on screen until text is drawn over them, so the
+    /// caller must repaint everything when placements move.
+    pub fn needs_clear(&self) -> bool {
+        self.protocol == Some(Protocol::Sixel)
+            && self.pending != self.shown
+            && !self.shown.is_empty()
+    }
+
+    pub fn flush(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.pending == self.shown {
+            return Ok(());
+        }
+        let mut text = String::new();
+        match self.protocol {
+            Some(Protocol::Kitty) => {
+                text.push_str("\x1b_Ga=d,d=a,q=2\x1b\\");
+                for placement in self.pending.clone() {
+                    self.kitty_place(&mut text, &placement);
+                }
+            }
+            Some(Protocol::Sixel) => {
+                for placement in self.pending.clone() {
+                    self.sixel_place(&mut text, &placement);
+                }
+            }
+            _ => {}
+        }
+        self.shown = self.pending.clone();
+        out.write_all(text.as_bytes())?;
+        out.flush()
+    }
+
+    /// Removes every kitty image, including its transmitted data.
+    pub fn clear(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.protocol == Some(Protocol::Kitty) {
+            out.write_all(b"\x1b_Ga=d,d=A,q=2\x1b\\")?;
+            out.flush()?;
+        }
+        self.kitty_ids.clear();
+        self.shown.clear();
+        Ok(())
+    }
+
+    fn scaled(&mut self, placement: &Placement) -> Option<&RgbaImage> {
+        let (cell_w, cell_h) = cell_pixels();
+        let key = (placement.path.clone(), placement.cols, placement.rows);
+        self.scaled
+            .entry(key)
+            .or_insert_with(|| {
+                load_scaled(
+                    &placement.path,
+                    placement.cols as u32 * cell_w,
+                    placement.rows as u32 * cell_h,
+                )
+            })
+            .as_ref()
+    }
+
+    fn kitty_place(&mut self, text: &mut String, placement: &Placement) {
+        let key = (placement.path.clone(), placement.cols, placement.rows);
+        let id = match self.kitty_ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = self.kitty_ids.len() as u32 + 1;
+                let Some(image) = self.scaled(placement) else {
+                    return;
+                };
+                let (width, height) = image.dimensions();
+                let data = STANDARD.encode(image.as_raw());
+                let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+                for (idx, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(idx + 1 < chunks.len());
+                    let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+                    if idx == 0 {
+                        let _ = write!(
+                            text,
+                            "\x1b_Ga=t,f=32,s={width},v={height},i={id},q=2,m={more};{chunk}\x1b\\"
+                        );
+                    } else {
+                        let _ = write!(text, "\x1b_Gm={more};{chunk}\x1b\\");
+                    }
+                }
+                self.kitty_ids.insert(key, id);
+                id
+            }
+        };
+        let (_, cell_h) = cell_pixels();
+        let _ = write!(
+            text,
+            "\x1b[{};{}H\x1b_Ga=p,i={id},y={},h={},c={},r={},C=1,q=2\x1b\\",
+            placement.y + 1,
+            placement.x + 1,
+            placement.first_row as u32 * cell_h,
+            placement.visible_rows as u32 * cell_h,
+            placement.cols,
+            placement.visible_rows,
+        );
+    }
+
+    fn sixel_place(&mut self, text: &mut String, placement: &Placement) {
+        if !self.sixels.contains_key(placement) {
+            let (_, cell_h) = cell_pixels();
+            let Some(image) = self.scaled(placement) else {
+                return;
+            };
+            let top = placement.first_row as u32 * cell_h;
+            // Sixel bands are six pixels tall; round down so the last band
+            // never spills into the row below the image.
+            let height = (placement.visible_rows as u32 * cell_h).min(image.height() - top) / 6 * 6;
+            let crop = image::imageops::crop_imm(image, 0, top, image.width(), height).to_image();
+            let encoded = encode_sixel(&crop);
+            if self.sixels.len() >= SIXEL_CACHE_LIMIT {
+                self.sixels.clear();
+            }
+            self.sixels.insert(placement.clone(), encoded);
+        }
+        let _ = write!(text, "\x1b[{};{}H", placement.y + 1, placement.x + 1);
+        text.push_str(&self.sixels[placement]);
+    }
+}
+
+/// Encodes an image as sixel with a 6x6x6 color cube; transparent pixels
+/// are left unpainted.
+fn encode_sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |value: u8| (value as u32 * 5 + 127) / 255;
+    let index = |x: u32, y: u32| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        (a >= 128).then(|| (level(r) * 36 + level(g) * 6 + level(b)) as usize)
+    };
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for color in 0..216u32 {
+        let percent = |step: u32| step * 100 / 5;
+        let _ = write!(
+            out,
+            "#{color};2;{};{};{}",
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        );
+    }
+    for band in (0..height).step_by(6) {
+        let mut columns: HashMap<usize, Vec<u8>> = HashMap::new();
+        for x in 0..width {
+            for bit in 0..6 {
+                let y = band + bit;
+                if y >= height {
+                    break;
+                }
+                if let Some(color) = index(x, y) {
+                    columns
+                        .entry(color)
+                        .or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << bit;
+                }
+            }
+        }
+        let mut colors: Vec<_> = columns.into_iter().collect();
+        colors.sort_by_key(|(color, _)| *color);
+        for (idx, (color, bits)) in colors.iter().enumerate() {
+            if idx > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{color}");
+            push_sixel_runs(&mut out, bits);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_sixel_runs(out: &mut String, bits: &[u8]) {
+    let mut idx = 0;
+    while idx < bits.len() {
+        let value = bits[idx];
+        let run = bits[idx..]
+            .iter()
+            .take_while(|&&next| next == value)
+            .count();
+        let ch = (value + 63) as char;
+        if run > 3 {
+            let _ = write!(out, "!{run}{ch}");
+        } else {
+            (0..run).for_each(|_| out.push(ch));
+        }
+        idx += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sixel_runs_are_compressed() {
+        let mut out = String::new();
+        push_sixel_runs(&mut out, &[0, 0, 0, 0, 0, 1, 1, 63]);
+        assert_eq!(out, "!5?@@~");
+    }
+
+    #[test]
+    fn only_local_destinations_resolve() {
+        let base = Path::new("/docs");
+        assert_eq!(
+            local_path(base, "img/chart.png?raw=1"),
+            Some(PathBuf::from("/docs/img/chart.png"))
+        );
+        assert_eq!(local_path(base, "https://example.com/a.png"), None);
+        assert_eq!(local_path(base, "data:image/png;base64,AAAA"), None);
+    }
+
+    #[test]
+    fn image_sizes_are_read_once_until_invalidated() {
+        let path = env::temp_dir().join(format!("md-viewer-size-{}.png", std::process::id()));
+        image::RgbImage::new(4, 2).save(&path).unwrap();
+        let mut graphics = Graphics::new(Some(Protocol::HalfBlocks));
+        let sizes = graphics.sizes();
+        assert_eq!(sizes.get(&path), Some((4, 2)));
+        image::RgbImage::new(6, 3).save(&path).unwrap();
+        assert_eq!(graphics.sizes().get(&path), Some((4, 2)));
+        graphics.invalidate();
+        assert_eq!(sizes.get(&path), Some((6, 3)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
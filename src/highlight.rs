@@ -0,0 +1,811 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    Type,
+    Function,
+    String,
+    Number,
+    Constant,
+    Comment,
+    Key,
+    Variable,
+    Operator,
+    Inserted,
+    Deleted,
+    Header,
+    Hunk,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Language {
+    Rust,
+    Python,
+    Shell,
+    Json,
+    Yaml,
+    Toml,
+    JavaScript,
+    Diff,
+}
+
+struct Grammar {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [&'static str],
+    multiline_quotes: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    constants: &'static [&'static str],
+    capitalized_types: bool,
+}
+
+const RUST: Grammar = Grammar {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &["\""],
+    multiline_quotes: &["\""],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "static", "struct", "super", "trait", "type", "unsafe",
+        "use", "where", "while", "yield",
+    ],
+    types: &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ],
+    constants: &["true", "false"],
+    capitalized_types: true,
+};
+
+const PYTHON: Grammar = Grammar {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &["\"\"\"", "'''", "\"", "'"],
+    multiline_quotes: &["\"\"\"", "'''"],
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+        "lambda", "match", "case", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+        "while", "with", "yield",
+    ],
+    types: &[
+        "int", "str", "float", "bool", "list", "dict", "set", "tuple", "bytes", "object",
+    ],
+    constants: &["True", "False", "None", "self", "cls"],
+    capitalized_types: true,
+};
+
+const SHELL: Grammar = Grammar {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &["\"", "'"],
+    multiline_quotes: &["\"", "'"],
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "local", "export", "readonly", "declare", "set", "unset",
+        "shift", "exit", "break", "continue", "source", "alias", "select", "time",
+    ],
+    types: &[],
+    constants: &["true", "false"],
+    capitalized_types: false,
+};
+
+const JSON: Grammar = Grammar {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &["\""],
+    multiline_quotes: &[],
+    keywords: &[],
+    types: &[],
+    constants: &["true", "false", "null"],
+    capitalized_types: false,
+};
+
+const YAML: Grammar = Grammar {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &["\"", "'"],
+    multiline_quotes: &[],
+    keywords: &[],
+    types: &[],
+    constants: &[
+        "true", "false", "True", "False", "TRUE", "FALSE", "null", "Null", "NULL", "yes", "no",
+        "on", "off", "~",
+    ],
+    capitalized_types: false,
+};
+
+const TOML: Grammar = Grammar {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &["\"\"\"", "'''", "\"", "'"],
+    multiline_quotes: &["\"\"\"", "'''"],
+    keywords: &[],
+    types: &[],
+    constants: &["true", "false", "inf", "nan"],
+    capitalized_types: false,
+};
+
+const JAVASCRIPT: Grammar = Grammar {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &["\"", "'", "`"],
+    multiline_quotes: &["`"],
+    keywords: &[
+        "abstract",
+        "as",
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "declare",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "get",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "keyof",
+        "let",
+        "namespace",
+        "new",
+        "of",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "return",
+        "set",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "try",
+        "type",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "with",
+        "yield",
+    ],
+    types: &[
+        "string", "number", "boolean", "any", "unknown", "never", "object", "bigint", "symbol",
+    ],
+    constants: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+    capitalized_types: true,
+};
+
+const DIFF: Grammar = Grammar {
+    line_comments: &[],
+    block_comment: None,
+    quotes: &[],
+    multiline_quotes: &[],
+    keywords: &[],
+    types: &[],
+    constants: &[],
+    capitalized_types: false,
+};
+
+impl Language {
+    fn from_info(info: &str) -> Option<Self> {
+        let name = info
+            .split(|ch: char| ch.is_whitespace() || ch == ',' || ch == '{')
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('.')
+            .to_ascii_lowercase();
+        let language = match name.as_str() {
+            "rust" | "rs" => Self::Rust,
+            "python" | "py" | "python3" | "py3" => Self::Python,
+            "sh" | "bash" | "shell" | "zsh" | "console" | "shell-session" | "shellsession" => {
+                Self::Shell
+            }
+            "json" | "jsonc" | "json5" => Self::Json,
+            "yaml" | "yml" => Self::Yaml,
+            "toml" => Self::Toml,
+            "javascript" | "js" | "jsx" | "mjs" | "cjs" | "typescript" | "ts" | "tsx" | "mts"
+            | "cts" => Self::JavaScript,
+            "diff" | "patch" | "udiff" => Self::Diff,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    fn grammar(self) -> &'static Grammar {
+        match self {
+            Self::Rust => &RUST,
+            Self::Python => &PYTHON,
+            Self::Shell => &SHELL,
+            Self::Json => &JSON,
+            Self::Yaml => &YAML,
+            Self::Toml => &TOML,
+            Self::JavaScript => &JAVASCRIPT,
+            Self::Diff => &DIFF,
+        }
+    }
+}
+
+enum LexState {
+    Normal,
+    BlockComment(&'static str),
+    String(String),
+}
+
+pub struct Highlighter {
+    language: Language,
+    state: LexState,
+}
+
+impl Highlighter {
+    pub fn for_info(info: &str) -> Option<Self> {
+        Language::from_info(info).map(|language| Self {
+            language,
+            state: LexState::Normal,
+        })
+    }
+
+    pub fn highlight_line<'a>(&mut self, line: &'a str) -> Vec<(TokenKind, &'a str)> {
+        let tokens = if self.language == Language::Diff {
+            vec![(diff_line_kind(line), 0..line.len())]
+        } else {
+            self.lex(line)
+        };
+        let mut merged: Vec<(TokenKind, Range<usize>)> = Vec::new();
+        for (kind, range) in tokens {
+            if range.is_empty() {
+                continue;
+            }
+            match merged.last_mut() {
+                Some((last_kind, last)) if *last_kind == kind && last.end == range.start => {
+                    last.end = range.end;
+                }
+                _ => merged.push((kind, range)),
+            }
+        }
+        merged
+            .into_iter()
+            .map(|(kind, range)| (kind, &line[range]))
+            .collect()
+    }
+
+    fn lex(&mut self, line: &str) -> Vec<(TokenKind, Range<usize>)> {
+        let grammar = self.language.grammar();
+        let bytes = line.as_bytes();
+        let mut tokens = Vec::new();
+        let mut pos = 0usize;
+
+        match std::mem::replace(&mut self.state, LexState::Normal) {
+            LexState::Normal => {}
+            LexState::BlockComment(end) => match line.find(end) {
+                Some(idx) => {
+                    tokens.push((TokenKind::Comment, 0..idx + end.len()));
+                    pos = idx + end.len();
+                }
+                None => {
+                    self.state = LexState::BlockComment(end);
+                    return vec![(TokenKind::Comment, 0..line.len())];
+                }
+            },
+            LexState::String(close) => match find_string_end(line, 0, &close, true) {
+                Some(end) => {
+                    tokens.push((TokenKind::String, 0..end));
+                    pos = end;
+                }
+                None => {
+                    self.state = LexState::String(close);
+                    return vec![(TokenKind::String, 0..line.len())];
+                }
+            },
+        }
+
+        if pos == 0 {
+            if let Some(kind) = self.line_prefix_kind(line) {
+                return vec![(kind, 0..line.len())];
+            }
+        }
+        if let Some(key) = self.leading_key(line, pos) {
+            tokens.push((TokenKind::Plain, pos..key.start));
+            pos = key.end;
+            tokens.push((TokenKind::Key, key));
+        }
+
+        let mut command_position = true;
+        while pos < line.len() {
+            let rest = &line[pos..];
+            let ch = rest.chars().next().unwrap_or(' ');
+
+            if ch.is_whitespace() {
+                let len = ch.len_utf8();
+                tokens.push((TokenKind::Plain, pos..pos + len));
+                pos += len;
+                continue;
+            }
+
+            if let Some(prefix) = grammar
+                .line_comments
+                .iter()
+                .find(|prefix| rest.starts_with(**prefix))
+            {
+                let word_start = pos == 0 || bytes[pos - 1].is_ascii_whitespace();
+                if !prefix.starts_with('#') || word_start {
+                    tokens.push((TokenKind::Comment, pos..line.len()));
+                    break;
+                }
+            }
+
+            if let Some((open, close)) = grammar.block_comment {
+                if let Some(body) = rest.strip_prefix(open) {
+                    match body.find(close) {
+                        Some(idx) => {
+                            let end = pos + open.len() + idx + close.len();
+                            tokens.push((TokenKind::Comment, pos..end));
+                            pos = end;
+                        }
+                        None => {
+                            tokens.push((TokenKind::Comment, pos..line.len()));
+                            self.state = LexState::BlockComment(close);
+                            pos = line.len();
+                        }
+                    }
+                    continue;
+                }
+            }
+
+            if self.language == Language::Rust {
+                if let Some(end) = rust_raw_string_end(line, pos) {
+                    match end {
+                        Ok(end) => {
+                            tokens.push((TokenKind::String, pos..end));
+                            pos = end;
+                        }
+                        Err(close) => {
+                            tokens.push((TokenKind::String, pos..line.len()));
+                            self.state = LexState::String(close);
+                            pos = line.len();
+                        }
+                    }
+                    continue;
+                }
+                if ch == '\'' {
+                    let end = rust_quote_end(line, pos);
+                    let kind = if line[pos..end].ends_with('\'') && end - pos > 1 {
+                        TokenKind::String
+                    } else {
+                        TokenKind::Type
+                    };
+                    tokens.push((kind, pos..end));
+                    pos = end;
+                    continue;
+                }
+            }
+
+            if let Some(quote) = grammar
+                .quotes
+                .iter()
+                .find(|quote| rest.starts_with(**quote))
+            {
+                let literal = match self.language {
+                    Language::Shell | Language::Yaml => *quote == "'",
+                    Language::Toml => quote.starts_with('\''),
+                    _ => false,
+                };
+                let start = pos + quote.len();
+                match find_string_end(line, start, quote, !literal) {
+                    Some(end) => {
+                        let kind = if self.string_is_key(line, end) {
+                            TokenKind::Key
+                        } else {
+                            TokenKind::String
+                        };
+                        tokens.push((kind, pos..end));
+                        pos = end;
+                    }
+                    None => {
+                        tokens.push((TokenKind::String, pos..line.len()));
+                        if grammar.multiline_quotes.contains(quote) {
+                            self.state = LexState::String((*quote).to_string());
+                        }
+                        pos = line.len();
+                    }
+                }
+                command_position = false;
+                continue;
+            }
+
+            if let Some(end) = self.variable_end(line, pos) {
+                tokens.push((TokenKind::Variable, pos..end));
+                pos = end;
+                command_position = false;
+                continue;
+            }
+
+            if ch.is_ascii_digit()
+                || (ch == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+            {
+                let end = number_end(line, pos, self.language);
+                tokens.push((TokenKind::Number, pos..end));
+                pos = end;
+                command_position = false;
+                continue;
+            }
+
+            if self.language == Language::Shell && !is_shell_operator(ch) {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || is_shell_operator(c) || c == '$')
+                    .map(|idx| pos + idx.max(ch.len_utf8()))
+                    .unwrap_or(line.len());
+                let word = &line[pos..end];
+                let kind = if grammar.keywords.contains(&word) {
+                    TokenKind::Keyword
+                } else if command_position && word != "$" {
+                    command_position = false;
+                    TokenKind::Function
+                } else if word.starts_with('-') {
+                    TokenKind::Constant
+                } else {
+                    TokenKind::Plain
+                };
+                tokens.push((kind, pos..end));
+                pos = end;
+                continue;
+            }
+
+            if is_ident_start(ch) {
+                let end = rest
+                    .find(|c: char| !is_ident_continue(c))
+                    .map(|idx| pos + idx)
+                    .unwrap_or(line.len());
+                let word = &line[pos..end];
+                let next = line[end..].chars().next();
+                let kind = if grammar.keywords.contains(&word) {
+                    TokenKind::Keyword
+                } else if grammar.constants.contains(&word) {
+                    TokenKind::Constant
+                } else if grammar.types.contains(&word) {
+                    TokenKind::Type
+                } else if next == Some('(')
+                    || (self.language == Language::Rust && next == Some('!'))
+                {
+                    TokenKind::Function
+                } else if grammar.capitalized_types && word.starts_with(char::is_uppercase) {
+                    TokenKind::Type
+                } else {
+                    TokenKind::Plain
+                };
+                tokens.push((kind, pos..end));
+                pos = end;
+                continue;
+            }
+
+            if self.language == Language::Python && ch == '@' {
+                let end = rest[1..]
+                    .find(|c: char| !is_ident_continue(c) && c != '.')
+                    .map(|idx| pos + 1 + idx)
+                    .unwrap_or(line.len());
+                tokens.push((TokenKind::Function, pos..end));
+                pos = end;
+                continue;
+            }
+
+            let len = ch.len_utf8();
+            let kind = if is_operator(ch) {
+                TokenKind::Operator
+            } else {
+                TokenKind::Plain
+            };
+            if self.language == Language::Shell && matches!(ch, '|' | ';' | '&' | '(') {
+                command_position = true;
+            }
+            tokens.push((kind, pos..pos + len));
+            pos += len;
+        }
+        tokens
+    }
+
+    fn line_prefix_kind(&self, line: &str) -> Option<TokenKind> {
+        let trimmed = line.trim_start();
+        match self.language {
+            Language::Toml if trimmed.starts_with('[') => Some(TokenKind::Header),
+            Language::Yaml if line == "---" || line == "..." => Some(TokenKind::Header),
+            _ => None,
+        }
+    }
+
+    fn leading_key(&self, line: &str, pos: usize) -> Option<Range<usize>> {
+        if pos != 0 {
+            return None;
+        }
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        match self.language {
+            Language::Toml => {
+                let eq = trimmed.find('=')?;
+                let key = trimmed[..eq].trim_end();
+                let valid = !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| is_ident_continue(c) || matches!(c, '-' | '.' | '"' | '\'' | ' '));
+                valid.then_some(indent..indent + key.len())
+            }
+            Language::Yaml => {
+                let body = trimmed.strip_prefix("- ").unwrap_or(trimmed);
+                if body.starts_with(['"', '\'', '#', '{', '[']) {
+                    return None;
+                }
+                let colon = body.find(':')?;
+                let after = &body[colon + 1..];
+                if !(after.is_empty() || after.starts_with(' ')) {
+                    return None;
+                }
+                let key_start = line.len() - body.len();
+                Some(key_start..key_start + colon)
+            }
+            _ => None,
+        }
+    }
+
+    fn string_is_key(&self, line: &str, end: usize) -> bool {
+        self.language == Language::Json && line[end..].trim_start().starts_with(':')
+    }
+
+    fn variable_end(&self, line: &str, pos: usize) -> Option<usize> {
+        let rest = &line[pos..];
+        match self.language {
+            Language::Shell if rest.starts_with("${") => Some(
+                rest.find('}')
+                    .map(|idx| pos + idx + 1)
+                    .unwrap_or(line.len()),
+            ),
+            Language::Shell if rest.starts_with('$') => {
+                let body = &rest[1..];
+                let special = body.starts_with(|c: char| "@*#?$!-0123456789".contains(c));
+                if special {
+                    return Some(pos + 2);
+                }
+                let len = body
+                    .find(|c: char| !is_ident_continue(c))
+                    .unwrap_or(body.len());
+                (len > 0).then_some(pos + 1 + len)
+            }
+            Language::Yaml if rest.starts_with(['&', '*']) => {
+                let body = &rest[1..];
+                let len = body
+                    .find(|c: char| c.is_whitespace() || c == ',')
+                    .unwrap_or(body.len());
+                (len > 0).then_some(pos + 1 + len)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn diff_line_kind(line: &str) -> TokenKind {
+    if line.starts_with("+++")
+        || line.starts_with("---")
+        || line.starts_with("diff ")
+        || line.starts_with("index ")
+    {
+        TokenKind::Header
+    } else if line.starts_with("@@") {
+        TokenKind::Hunk
+    } else if line.starts_with('+') {
+        TokenKind::Inserted
+    } else if line.starts_with('-') {
+        TokenKind::Deleted
+    } else {
+        TokenKind::Plain
+    }
+}
+
+fn find_string_end(line: &str, start: usize, close: &str, escapes: bool) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut idx = start;
+    while idx < line.len() {
+        if escapes && bytes[idx] == b'\\' {
+            idx += 1;
+            idx += line[idx..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if line[idx..].starts_with(close) {
+            return Some(idx + close.len());
+        }
+        idx += line[idx..].chars().next().map_or(1, char::len_utf8);
+    }
+    None
+}
+
+fn rust_raw_string_end(line: &str, pos: usize) -> Option<Result<usize, String>> {
+    let rest = &line[pos..];
+    let after_prefix = rest.strip_prefix("br").or_else(|| rest.strip_prefix('r'))?;
+    if pos > 0 && is_ident_continue(line[..pos].chars().next_back()?) {
+        return None;
+    }
+    let hashes = after_prefix.len() - after_prefix.trim_start_matches('#').len();
+    if !after_prefix[hashes..].starts_with('"') {
+        return None;
+    }
+    let close = format!("\"{}", "#".repeat(hashes));
+    let body_start = pos + (rest.len() - after_prefix.len()) + hashes + 1;
+    Some(
+        line[body_start..]
+            .find(&close)
+            .map(|idx| body_start + idx + close.len())
+            .ok_or(close),
+    )
+}
+
+fn rust_quote_end(line: &str, pos: usize) -> usize {
+    let rest = &line[pos + 1..];
+    if let Some(escaped) = rest.strip_prefix('\\') {
+        if let Some(idx) = escaped.find('\'') {
+            return pos + 1 + 1 + idx + 1;
+        }
+    }
+    let mut chars = rest.char_indices();
+    if let (Some((_, _)), Some((idx, '\''))) = (chars.next(), chars.next()) {
+        return pos + 1 + idx + 1;
+    }
+    rest.find(|c: char| !is_ident_continue(c))
+        .map(|idx| pos + 1 + idx)
+        .unwrap_or(line.len())
+}
+
+fn number_end(line: &str, pos: usize, language: Language) -> usize {
+    let date_like = matches!(language, Language::Toml | Language::Yaml);
+    let bytes = line.as_bytes();
+    let mut idx = pos;
+    while idx < bytes.len() {
+        let b = bytes[idx];
+        let keep = b.is_ascii_alphanumeric()
+            || b == b'_'
+            || (b == b'.' && bytes.get(idx + 1).is_some_and(u8::is_ascii_digit))
+            || (date_like && matches!(b, b'-' | b':' | b'+'));
+        if !keep {
+            break;
+        }
+        idx += 1;
+    }
+    idx.max(pos + 1)
+}
+
+fn is_ident_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+fn is_ident_continue(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn is_operator(ch: char) -> bool {
+    "+-*/%=<>!&|^~?:".contains(ch)
+}
+
+fn is_shell_operator(ch: char) -> bool {
+    "|&;<>()`\"'".contains(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(info: &str, lines: &[&str]) -> Vec<Vec<(TokenKind, String)>> {
+        let mut highlighter = Highlighter::for_info(info).expect("language supported");
+        lines
+            .iter()
+            .map(|line| {
+                highlighter
+                    .highlight_line(line)
+                    .into_iter()
+                    .map(|(kind, text)| (kind, text.to_string()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn has(tokens: &[(TokenKind, String)], kind: TokenKind, text: &str) -> bool {
+        tokens.iter().any(|(k, t)| *k == kind && t == text)
+    }
+
+    #[test]
+    fn rust_tokens_are_classified() {
+        let lines = kinds(
+            "rust",
+            &["fn main() { let x: u32 = 42; println!(\"hi\"); } // done"],
+        );
+        let line = &lines[0];
+        assert!(has(line, TokenKind::Keyword, "fn"));
+        assert!(has(line, TokenKind::Function, "main"));
+        assert!(has(line, TokenKind::Type, "u32"));
+        assert!(has(line, TokenKind::Number, "42"));
+        assert!(has(line, TokenKind::Function, "println"));
+        assert!(has(line, TokenKind::String, "\"hi\""));
+        assert!(has(line, TokenKind::Comment, "// done"));
+    }
+
+    #[test]
+    fn block_comments_and_strings_span_lines() {
+        let lines = kinds("python", &["x = \"\"\"start", "middle", "end\"\"\" + 1"]);
+        assert!(has(&lines[1], TokenKind::String, "middle"));
+        assert!(has(&lines[2], TokenKind::String, "end\"\"\""));
+        assert!(has(&lines[2], TokenKind::Number, "1"));
+
+        let lines = kinds("ts", &["/* a", "b */ const y = 1;"]);
+        assert!(has(&lines[0], TokenKind::Comment, "/* a"));
+        assert!(has(&lines[1], TokenKind::Comment, "b */"));
+        assert!(has(&lines[1], TokenKind::Keyword, "const"));
+    }
+
+    #[test]
+    fn data_formats_highlight_keys() {
+        let json = kinds("json", &["{\"name\": \"md\", \"ok\": true}"]);
+        assert!(has(&json[0], TokenKind::Key, "\"name\""));
+        assert!(has(&json[0], TokenKind::String, "\"md\""));
+        assert!(has(&json[0], TokenKind::Constant, "true"));
+
+        let yaml = kinds("yaml", &["  - name: value # note"]);
+        assert!(has(&yaml[0], TokenKind::Key, "name"));
+        assert!(has(&yaml[0], TokenKind::Comment, "# note"));
+
+        let toml = kinds("toml", &["[package]", "edition = \"2021\""]);
+        assert!(has(&toml[0], TokenKind::Header, "[package]"));
+        assert!(has(&toml[1], TokenKind::Key, "edition"));
+    }
+
+    #[test]
+    fn shell_and_diff_lines() {
+        let shell = kinds("bash", &["cargo run --release | grep \"$HOME\" # run"]);
+        assert!(has(&shell[0], TokenKind::Function, "cargo"));
+        assert!(has(&shell[0], TokenKind::Constant, "--release"));
+        assert!(has(&shell[0], TokenKind::Function, "grep"));
+        assert!(has(&shell[0], TokenKind::Comment, "# run"));
+
+        let diff = kinds("diff", &["@@ -1 +1 @@", "-old", "+new"]);
+        assert_eq!(diff[0][0].0, TokenKind::Hunk);
+        assert_eq!(diff[1][0].0, TokenKind::Deleted);
+        assert_eq!(diff[2][0].0, TokenKind::Inserted);
+    }
+
+    #[test]
+    fn escaped_non_ascii_characters_stay_inside_strings() {
+        for info in ["rust", "python", "bash", "json", "yaml", "toml", "js"] {
+            let lines = kinds(info, &["x = [\"\\é\", \"\\\u{1F600}\"]"]);
+            assert!(has(&lines[0], TokenKind::String, "\"\\é\""), "{info}");
+            assert!(
+                has(&lines[0], TokenKind::String, "\"\\\u{1F600}\""),
+                "{info}"
+            );
+        }
+        let lines = kinds("rust", &["let s = \"start", "\\é\" + 1;"]);
+        assert!(has(&lines[1], TokenKind::String, "\\é\""));
+        assert!(has(&lines[1], TokenKind::Number, "1"));
+    }
+
+    #[test]
+    fn unknown_languages_are_not_highlighted() {
+        assert!(Highlighter::for_info("brainfuck").is_none());
+        assert!(Highlighter::for_info("rust,ignore").is_some());
+    }
+}
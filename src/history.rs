@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use crate::anchor::ScrollAnchor;
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    pub anchor: Option<ScrollAnchor>,
+}
+
+#[derive(Default)]
+pub struct History {
+    back: Vec<HistoryEntry>,
+    forward: Vec<HistoryEntry>,
+    /// Documents from the command line not opened yet, the next one last.
+    /// They outlive `visit`, unlike forward entries.
+    queued: Vec<HistoryEntry>,
+    /// Whether the last `forward` took its target from `queued`.
+    took_queued: bool,
+}
+
+impl History {
+    pub fn len_back(&self) -> usize {
+        self.back.len()
+    }
+
+    pub fn len_forward(&self) -> usize {
+        self.forward.len() + self.queued.len()
+    }
+
+    /// Records `current` before navigating to a new document. Any forward
+    /// entries are discarded, like a browser; queued documents stay.
+    pub fn visit(&mut self, current: HistoryEntry) {
+        self.back.push(current);
+        self.forward.clear();
+    }
+
+    /// Queues documents to be reached with forward navigation, in order,
+    /// once the forward entries run out.
+    pub fn queue(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        let mut entries: Vec<HistoryEntry> = paths
+            .into_iter()
+            .map(|path| HistoryEntry { path, anchor: None })
+            .collect();
+        entries.reverse();
+        self.queued.extend(entries);
+    }
+
+    pub fn back(&mut self, current: HistoryEntry) -> Option<HistoryEntry> {
+        let previous = self.back.pop()?;
+        self.forward.push(current);
+        Some(previous)
+    }
+
+    pub fn forward(&mut self, current: HistoryEntry) -> Option<HistoryEntry> {
+        let next = match self.forward.pop() {
+            Some(next) => {
+                self.took_queued = false;
+                next
+            }
+            None => {
+                self.took_queued = true;
+                self.queued.pop()?
+            }
+        };
+        self.back.push(current);
+        Some(next)
+    }
+
+    /// Undoes a `back` whose target could not be opened.
+    pub fn restore_back(&mut self, target: HistoryEntry) {
+        self.forward.pop();
+        self.back.push(target);
+    }
+
+    /// Undoes a `forward` whose target could not be opened.
+    pub fn restore_forward(&mut self, target: HistoryEntry) {
+        self.back.pop();
+        if self.took_queued {
+            self.queued.push(target);
+        } else {
+            self.forward.push(target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str) -> HistoryEntry {
+        HistoryEntry {
+            path: PathBuf::from(path),
+            anchor: None,
+        }
+    }
+
+    fn path_of(entry: Option<HistoryEntry>) -> Option<PathBuf> {
+        entry.map(|entry| entry.path)
+    }
+
+    #[test]
+    fn back_and_forward_walk_visited_documents() {
+        let mut history = History::default();
+        history.visit(entry("a.md"));
+        history.visit(entry("b.md"));
+        assert_eq!(path_of(history.back(entry("c.md"))), Some("b.md".into()));
+        assert_eq!(path_of(history.back(entry("b.md"))), Some("a.md".into()));
+        assert!(history.back(entry("a.md")).is_none());
+        assert_eq!(path_of(history.forward(entry("a.md"))), Some("b.md".into()));
+        history.visit(entry("b.md"));
+        assert_eq!(history.len_forward(), 0);
+    }
+
+    #[test]
+    fn queued_paths_open_in_order() {
+        let mut history = History::default();
+        history.queue(["b.md".into(), "c.md".into()]);
+        assert_eq!(path_of(history.forward(entry("a.md"))), Some("b.md".into()));
+        assert_eq!(path_of(history.forward(entry("b.md"))), Some("c.md".into()));
+        assert_eq!(history.len_back(), 2);
+    }
+
+    #[test]
+    fn visiting_a_link_keeps_queued_paths() {
+        let mut history = History::default();
+        history.queue(["b.md".into(), "c.md".into()]);
+        history.visit(entry("a.md"));
+        assert_eq!(history.len_forward(), 2);
+        assert_eq!(
+            path_of(history.forward(entry("link.md"))),
+            Some("b.md".into())
+        );
+        assert_eq!(path_of(history.back(entry("b.md"))), Some("link.md".into()));
+        history.visit(entry("link.md"));
+        assert_eq!(path_of(history.forward(entry("x.md"))), Some("c.md".into()));
+        history.restore_forward(entry("c.md"));
+        assert_eq!(path_of(history.forward(entry("x.md"))), Some("c.md".into()));
+        assert!(history.forward(entry("c.md")).is_none());
+    }
+}
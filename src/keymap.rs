@@ -0,0 +1,851 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    ScrollDown,
+    ScrollUp,
+    ScrollLeft,
+    ScrollRight,
+    ViewDown,
+    ViewUp,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    Top,
+    Bottom,
+    Percent,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    SetMark,
+    JumpMark,
+    JumpBack,
+    JumpForward,
+    NextHeading,
+    PrevHeading,
+    NextSection,
+    PrevSection,
+    Search,
+    NextMatch,
+    PrevMatch,
+    NextLink,
+    PrevLink,
+    Open,
+    Cancel,
+    Back,
+    Forward,
+    ToggleOutline,
+    ToggleMouse,
+    ToggleWatch,
+    ToggleWrap,
+    CopyCode,
+    Select,
+    Yank,
+    YankSource,
+    Reload,
+    ToggleHelp,
+    Quit,
+}
+
+pub struct ActionInfo {
+    pub action: Action,
+    /// Name used in the `[keys]` table of the config file.
+    pub name: &'static str,
+    pub section: &'static str,
+    pub help: &'static str,
+    defaults: &'static [&'static str],
+}
+
+const fn info(
+    action: Action,
+    name: &'static str,
+    section: &'static str,
+    help: &'static str,
+    defaults: &'static [&'static str],
+) -> ActionInfo {
+    ActionInfo {
+        action,
+        name,
+        section,
+        help,
+        defaults,
+    }
+}
+
+const NAVIGATION: &str = "Navigation";
+const SEARCH: &str = "Search";
+const LINKS: &str = "Links and documents";
+const VIEW: &str = "View";
+
+pub const ACTIONS: &[ActionInfo] = &[
+    info(
+        Action::ScrollDown,
+        "scroll-down",
+        NAVIGATION,
+        "scroll down one line",
+        &["j", "Down"],
+    ),
+    info(
+        Action::ScrollUp,
+        "scroll-up",
+        NAVIGATION,
+        "scroll up one line",
+        &["k", "Up"],
+    ),
+    info(
+        Action::ScrollLeft,
+        "scroll-left",
+        NAVIGATION,
+        "scroll an unwrapped table or code block left",
+        &["h", "Left"],
+    ),
+    info(
+        Action::ScrollRight,
+        "scroll-right",
+        NAVIGATION,
+        "scroll an unwrapped table or code block right",
+        &["l", "Right"],
+    ),
+    info(
+        Action::ViewDown,
+        "view-down",
+        NAVIGATION,
+        "scroll down one line, leaving the cursor in place",
+        &["Ctrl-e"],
+    ),
+    info(
+        Action::ViewUp,
+        "view-up",
+        NAVIGATION,
+        "scroll up one line, leaving the cursor in place",
+        &["Ctrl-y"],
+    ),
+    info(
+        Action::PageDown,
+        "page-down",
+        NAVIGATION,
+        "page down",
+        &["Space", "PageDown"],
+    ),
+    info(
+        Action::PageUp,
+        "page-up",
+        NAVIGATION,
+        "page up",
+        &["p", "PageUp"],
+    ),
+    info(
+        Action::HalfPageDown,
+        "half-page-down",
+        NAVIGATION,
+        "half a page down (a count sets the distance)",
+        &["Ctrl-d"],
+    ),
+    info(
+        Action::HalfPageUp,
+        "half-page-up",
+        NAVIGATION,
+        "half a page up (a count sets the distance)",
+        &["Ctrl-u"],
+    ),
+    info(
+        Action::Top,
+        "top",
+        NAVIGATION,
+        "jump to the top (or to source line N)",
+        &["g", "Home"],
+    ),
+    info(
+        Action::Bottom,
+        "bottom",
+        NAVIGATION,
+        "jump to the bottom (or to source line N)",
+        &["G", "End"],
+    ),
+    info(
+        Action::Percent,
+        "percent",
+        NAVIGATION,
+        "jump N% into the document",
+        &["%"],
+    ),
+    info(
+        Action::ScreenTop,
+        "screen-top",
+        NAVIGATION,
+        "cursor to the top of the screen",
+        &["H"],
+    ),
+    info(
+        Action::ScreenMiddle,
+        "screen-middle",
+        NAVIGATION,
+        "cursor to the middle of the screen",
+        &["M"],
+    ),
+    info(
+        Action::ScreenBottom,
+        "screen-bottom",
+        NAVIGATION,
+        "cursor to the bottom of the screen",
+        &["L"],
+    ),
+    info(
+        Action::SetMark,
+        "set-mark",
+        NAVIGATION,
+        "set mark <letter> (uppercase marks work across files)",
+        &["m"],
+    ),
+    info(
+        Action::JumpMark,
+        "jump-mark",
+        NAVIGATION,
+        "return to mark <letter>",
+        &["'", "`"],
+    ),
+    info(
+        Action::JumpBack,
+        "jump-back",
+        NAVIGATION,
+        "back to where the last jump started",
+        &["Ctrl-o"],
+    ),
+    info(
+        Action::JumpForward,
+        "jump-forward",
+        NAVIGATION,
+        "forward again through the jump list",
+        &["Ctrl-i"],
+    ),
+    info(
+        Action::NextHeading,
+        "next-heading",
+        NAVIGATION,
+        "next heading",
+        &["]]"],
+    ),
+    info(
+        Action::PrevHeading,
+        "prev-heading",
+        NAVIGATION,
+        "previous heading",
+        &["[["],
+    ),
+    info(
+        Action::NextSection,
+        "next-section",
+        NAVIGATION,
+        "next H1 or H2 section",
+        &["}"],
+    ),
+    info(
+        Action::PrevSection,
+        "prev-section",
+        NAVIGATION,
+        "previous H1 or H2 section",
+        &["{"],
+    ),
+    info(
+        Action::Search,
+        "search",
+        SEARCH,
+        "search the document",
+        &["/"],
+    ),
+    info(
+        Action::NextMatch,
+        "next-match",
+        SEARCH,
+        "next match",
+        &["n"],
+    ),
+    info(
+        Action::PrevMatch,
+        "prev-match",
+        SEARCH,
+        "previous match",
+        &["N"],
+    ),
+    info(
+        Action::NextLink,
+        "next-link",
+        LINKS,
+        "focus the next link",
+        &["Tab"],
+    ),
+    info(
+        Action::PrevLink,
+        "prev-link",
+        LINKS,
+        "focus the previous link",
+        &["Shift-Tab"],
+    ),
+    info(
+        Action::Open,
+        "open",
+        LINKS,
+        "follow the focused link or outline entry, or zoom the table or code block at the cursor",
+        &["Enter"],
+    ),
+    info(
+        Action::Cancel,
+        "cancel",
+        LINKS,
+        "clear link focus or leave the outline",
+        &["Esc"],
+    ),
+    info(
+        Action::Back,
+        "back",
+        LINKS,
+        "previous document",
+        &["Backspace", "Alt-Left"],
+    ),
+    info(
+        Action::Forward,
+        "forward",
+        LINKS,
+        "next document",
+        &["Alt-Right"],
+    ),
+    info(
+        Action::ToggleOutline,
+        "toggle-outline",
+        VIEW,
+        "open / focus / close the outline",
+        &["t"],
+    ),
+    info(
+        Action::ToggleMouse,
+        "toggle-mouse",
+        VIEW,
+        "toggle mouse support",
+        &["Alt-m"],
+    ),
+    info(
+        Action::ToggleWatch,
+        "toggle-watch",
+        VIEW,
+        "toggle watch mode",
+        &["w"],
+    ),
+    info(
+        Action::ToggleWrap,
+        "toggle-wrap",
+        VIEW,
+        "show the table or code block at the cursor at full width",
+        &["W"],
+    ),
+    info(
+        Action::CopyCode,
+        "copy-code",
+        VIEW,
+        "copy the code block nearest the top of the screen",
+        &["c"],
+    ),
+    info(
+        Action::Select,
+        "select",
+        VIEW,
+        "start / stop selecting lines from the cursor",
+        &["v", "V"],
+    ),
+    info(
+        Action::Yank,
+        "yank",
+        VIEW,
+        "copy the selected lines (or the cursor line) as shown",
+        &["y"],
+    ),
+    info(
+        Action::YankSource,
+        "yank-source",
+        VIEW,
+        "copy the markdown source of the selected lines",
+        &["Y"],
+    ),
+    info(Action::Reload, "reload", VIEW, "reload the file", &["r"]),
+    info(Action::ToggleHelp, "help", VIEW, "toggle this help", &["?"]),
+    info(Action::Quit, "quit", VIEW, "quit", &["q", "Ctrl-c"]),
+];
+
+/// Actions shown in the status bar hint, with the label used there.
+pub const STATUS_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::PageDown], "page ↓"),
+    (&[Action::PageUp], "page ↑"),
+    (&[Action::ScrollDown, Action::ScrollUp], "line"),
+    (&[Action::Top, Action::Bottom], "top/end"),
+    (&[Action::Search], "search"),
+    (&[Action::Reload], "reload"),
+    (&[Action::Quit], "quit"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already folded into the character (or into BackTab), and
+        // terminals disagree on whether they report it as well.
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+pub enum KeyResult {
+    /// The action and the count typed before it, if any.
+    Action(Action, Option<usize>),
+    Pending,
+    Unbound,
+}
+
+/// A `[keys]` entry that cannot be used, and the action it belongs to.
+#[derive(Debug)]
+pub struct BindingError {
+    pub action: String,
+    pub message: String,
+}
+
+impl BindingError {
+    fn new(action: &str, message: String) -> Self {
+        Self {
+            action: action.to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.action, self.message)
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+    pending: Vec<KeyPress>,
+    count: Option<usize>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::with_overrides(std::iter::empty::<(&str, &[String])>())
+            .expect("default key bindings parse")
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap, replacing the defaults of every action named in
+    /// `overrides` with the given key specs. A key bound to two actions, or
+    /// one that starts a longer chord of another action, is an error that
+    /// blames an overridden action.
+    pub fn with_overrides<'a, I, S>(overrides: I) -> Result<Self, BindingError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a [S])>,
+        S: AsRef<str> + 'a,
+    {
+        let mut specs: Vec<(Action, Vec<String>, bool)> = ACTIONS
+            .iter()
+            .map(|info| {
+                let keys = info.defaults.iter().map(|key| key.to_string()).collect();
+                (info.action, keys, false)
+            })
+            .collect();
+        for (name, keys) in overrides {
+            let info = ACTIONS
+                .iter()
+                .find(|info| info.name == name)
+                .ok_or_else(|| BindingError::new(name, format!("unknown action '{name}'")))?;
+            let entry = specs
+                .iter_mut()
+                .find(|(action, _, _)| *action == info.action)
+                .expect("every action has defaults");
+            entry.1 = keys.iter().map(|key| key.as_ref().to_string()).collect();
+            entry.2 = true;
+        }
+        let mut bindings: Vec<(Vec<KeyPress>, Action)> = Vec::new();
+        let mut overridden = Vec::new();
+        for (action, keys, is_override) in specs {
+            let name = action_info(action).name;
+            if is_override {
+                overridden.push(action);
+            }
+            for spec in keys {
+                let chord = parse_chord(&spec).map_err(|err| BindingError::new(name, err))?;
+                if !bindings.contains(&(chord.clone(), action)) {
+                    bindings.push((chord, action));
+                }
+            }
+        }
+        for (idx, (chord, action)) in bindings.iter().enumerate() {
+            for (other, other_action) in &bindings[idx + 1..] {
+                if action == other_action {
+                    continue;
+                }
+                let (short, long) = if chord.len() <= other.len() {
+                    ((chord, action), (other, other_action))
+                } else {
+                    ((other, other_action), (chord, action))
+                };
+                if !long.0.starts_with(short.0) {
+                    continue;
+                }
+                let (name, long_name) = (action_info(*short.1).name, action_info(*long.1).name);
+                let message = if short.0.len() == long.0.len() {
+                    format!(
+                        "'{}' is bound to both {name} and {long_name}",
+                        chord_label(chord)
+                    )
+                } else {
+                    format!(
+                        "'{}' for {long_name} can never be typed: '{}' is bound to {name}",
+                        chord_label(long.0),
+                        chord_label(short.0)
+                    )
+                };
+                let blamed = if overridden.contains(other_action) {
+                    other_action
+                } else {
+                    action
+                };
+                return Err(BindingError::new(action_info(*blamed).name, message));
+            }
+        }
+        Ok(Self {
+            bindings,
+            pending: Vec::new(),
+            count: None,
+        })
+    }
+
+    /// Feeds one key press; counts and multi-key chords report `Pending`
+    /// until an action completes them.
+    pub fn press(&mut self, key: KeyPress) -> KeyResult {
+        if let Some(digit) = self.count_digit(key) {
+            let count = self.count.unwrap_or(0).saturating_mul(10);
+            self.count = Some(count.saturating_add(digit));
+            return KeyResult::Pending;
+        }
+        self.pending.push(key);
+        loop {
+            if let Some((_, action)) = self
+                .bindings
+                .iter()
+                .find(|(chord, _)| *chord == self.pending)
+            {
+                self.pending.clear();
+                return KeyResult::Action(*action, self.count.take());
+            }
+            if self
+                .bindings
+                .iter()
+                .any(|(chord, _)| chord.starts_with(&self.pending))
+            {
+                return KeyResult::Pending;
+            }
+            if self.pending.len() == 1 {
+                self.pending.clear();
+                self.count = None;
+                return KeyResult::Unbound;
+            }
+            // An abandoned chord: start over from the key that broke it.
+            self.pending = vec![key];
+        }
+    }
+
+    /// Digits start or extend a count unless they are bound themselves; `0`
+    /// only counts once a count has started.
+    fn count_digit(&self, key: KeyPress) -> Option<usize> {
+        let KeyCode::Char(ch) = key.code else {
+            return None;
+        };
+        let digit = ch.to_digit(10)? as usize;
+        let starts_count = digit != 0 || self.count.is_some();
+        let bound = self.bindings.iter().any(|(chord, _)| chord[0] == key);
+        (starts_count && key.modifiers.is_empty() && self.pending.is_empty() && !bound)
+            .then_some(digit)
+    }
+
+    /// The count and chord typed so far, e.g. `12` or `]`.
+    pub fn pending_keys(&self) -> String {
+        let mut text = self
+            .count
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        text.push_str(&chord_label(&self.pending));
+        text
+    }
+
+    pub fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(chord, _)| chord_label(chord))
+            .collect()
+    }
+
+    /// The first key bound to `action`, for compact hints.
+    pub fn key_for(&self, action: Action) -> String {
+        self.keys_for(action)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| String::from("(unbound)"))
+    }
+}
+
+pub fn action_info(action: Action) -> &'static ActionInfo {
+    ACTIONS
+        .iter()
+        .find(|info| info.action == action)
+        .expect("every action is listed")
+}
+
+/// Parses `"j"`, `"Ctrl-d"`, `"Alt-Left"`, `"gg"` or `"g g"` into key presses.
+pub fn parse_chord(spec: &str) -> Result<Vec<KeyPress>, String> {
+    let mut chord = Vec::new();
+    for token in spec.split_whitespace() {
+        match parse_key(token) {
+            Ok(key) => chord.push(key),
+            Err(err) if token.chars().count() > 1 && !token.contains(['-', '+']) => {
+                for ch in token.chars() {
+                    chord.push(parse_key(&ch.to_string()).map_err(|_| err.clone())?);
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    if chord.is_empty() {
+        return Err(String::from("empty key binding"));
+    }
+    Ok(chord)
+}
+
+fn parse_key(token: &str) -> Result<KeyPress, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = token;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        let prefix = ["ctrl-", "ctrl+", "c-"]
+            .iter()
+            .map(|p| (p, KeyModifiers::CONTROL))
+            .chain(
+                ["alt-", "alt+", "m-", "a-"]
+                    .iter()
+                    .map(|p| (p, KeyModifiers::ALT)),
+            )
+            .chain(
+                ["shift-", "shift+", "s-"]
+                    .iter()
+                    .map(|p| (p, KeyModifiers::SHIFT)),
+            )
+            .find(|(p, _)| lower.starts_with(**p) && rest.len() > p.len());
+        match prefix {
+            Some((p, modifier)) => {
+                modifiers |= modifier;
+                rest = &rest[p.len()..];
+            }
+            None => break,
+        }
+    }
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" | "bs" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    // Shift reaches us folded into the character, so only
+                    // letters have a shifted form we can name.
+                    (Some(ch), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        if !ch.is_ascii_alphabetic() {
+                            return Err(format!(
+                                "'{token}': Shift- only combines with letters and named keys; write the shifted character instead"
+                            ));
+                        }
+                        modifiers -= KeyModifiers::SHIFT;
+                        KeyCode::Char(ch.to_ascii_uppercase())
+                    }
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => return Err(format!("unknown key '{token}'")),
+                }
+            }
+        },
+    };
+    Ok(KeyPress::new(code, modifiers))
+}
+
+fn chord_label(chord: &[KeyPress]) -> String {
+    let plain_chars = chord.len() > 1
+        && chord.iter().all(|key| {
+            key.modifiers.is_empty() && matches!(key.code, KeyCode::Char(ch) if ch != ' ')
+        });
+    let separator = if plain_chars { "" } else { " " };
+    chord
+        .iter()
+        .map(key_label)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn key_label(key: &KeyPress) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(ch) => ch.to_string(),
+        KeyCode::Enter => String::from("Enter"),
+        KeyCode::Esc => String::from("Esc"),
+        KeyCode::Tab => String::from("Tab"),
+        KeyCode::BackTab => String::from("Shift+Tab"),
+        KeyCode::Backspace => String::from("Backspace"),
+        KeyCode::Up => String::from("Up"),
+        KeyCode::Down => String::from("Down"),
+        KeyCode::Left => String::from("Left"),
+        KeyCode::Right => String::from("Right"),
+        KeyCode::Home => String::from("Home"),
+        KeyCode::End => String::from("End"),
+        KeyCode::PageUp => String::from("PgUp"),
+        KeyCode::PageDown => String::from("PgDn"),
+        KeyCode::Delete => String::from("Del"),
+        KeyCode::Insert => String::from("Ins"),
+        KeyCode::F(n) => format!("F{n}"),
+        other => format!("{other:?}"),
+    };
+    let mut label = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        label.push_str("Ctrl+");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        label.push_str("Alt+");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        label.push_str("Shift+");
+    }
+    label.push_str(&name);
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(keymap: &mut Keymap, code: KeyCode) -> Option<Action> {
+        match keymap.press(KeyPress::new(code, KeyModifiers::NONE)) {
+            KeyResult::Action(action, _) => Some(action),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn chords_wait_for_their_second_key() {
+        let mut keymap = Keymap::default();
+        assert_eq!(press(&mut keymap, KeyCode::Char(']')), None);
+        assert_eq!(
+            press(&mut keymap, KeyCode::Char(']')),
+            Some(Action::NextHeading)
+        );
+        assert_eq!(press(&mut keymap, KeyCode::Char('[')), None);
+        assert_eq!(
+            press(&mut keymap, KeyCode::Char('j')),
+            Some(Action::ScrollDown)
+        );
+    }
+
+    #[test]
+    fn digits_build_a_count() {
+        let mut keymap = Keymap::default();
+        let mut key = |ch| keymap.press(KeyPress::new(KeyCode::Char(ch), KeyModifiers::NONE));
+        assert!(matches!(key('1'), KeyResult::Pending));
+        assert!(matches!(key('0'), KeyResult::Pending));
+        assert!(matches!(
+            key('j'),
+            KeyResult::Action(Action::ScrollDown, Some(10))
+        ));
+        assert!(matches!(key('0'), KeyResult::Unbound));
+        assert!(matches!(
+            key('j'),
+            KeyResult::Action(Action::ScrollDown, None)
+        ));
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let keys = [String::from("gg"), String::from("Ctrl-Home")];
+        let mut keymap = Keymap::with_overrides([("top", &keys[..])]).unwrap();
+        assert_eq!(keymap.keys_for(Action::Top), vec!["gg", "Ctrl+Home"]);
+        assert_eq!(press(&mut keymap, KeyCode::Char('g')), None);
+        assert_eq!(press(&mut keymap, KeyCode::Char('g')), Some(Action::Top));
+        assert_eq!(press(&mut keymap, KeyCode::Home), None);
+
+        let bad = [String::from("Hyper-x")];
+        assert!(Keymap::with_overrides([("top", &bad[..])]).is_err());
+        assert!(Keymap::with_overrides([("teleport", &keys[..])]).is_err());
+    }
+
+    #[test]
+    fn parses_named_and_modified_keys() {
+        let chord = parse_chord("Ctrl-d").unwrap();
+        assert_eq!(
+            chord,
+            vec![KeyPress::new(KeyCode::Char('d'), KeyModifiers::CONTROL)]
+        );
+        assert_eq!(parse_chord("Shift-Tab").unwrap()[0].code, KeyCode::BackTab);
+        assert_eq!(chord_label(&parse_chord("g g").unwrap()), "gg");
+        assert_eq!(chord_label(&parse_chord("Alt-Left").unwrap()), "Alt+Left");
+        assert_eq!(parse_chord("Shift-j").unwrap(), parse_chord("J").unwrap());
+        assert_eq!(
+            parse_chord("Ctrl-Shift-g").unwrap(),
+            vec![KeyPress::new(KeyCode::Char('G'), KeyModifiers::CONTROL)]
+        );
+        assert!(parse_chord("Shift-1").is_err());
+    }
+
+    #[test]
+    fn conflicting_bindings_are_rejected() {
+        let gg = [String::from("gg")];
+        let err = Keymap::with_overrides([("quit", &gg[..])]).err().unwrap();
+        assert_eq!(err.action, "quit");
+        assert_eq!(
+            err.to_string(),
+            "quit: 'gg' for quit can never be typed: 'g' is bound to top"
+        );
+
+        let j = [String::from("j")];
+        let err = Keymap::with_overrides([("quit", &j[..])]).err().unwrap();
+        assert_eq!(err.action, "quit");
+        assert_eq!(err.message, "'j' is bound to both scroll-down and quit");
+
+        let down = [String::from("Down")];
+        assert!(Keymap::with_overrides([("scroll-down", &down[..]), ("quit", &j[..])]).is_ok());
+        let twice = [String::from("j"), String::from("j")];
+        assert!(Keymap::with_overrides([("quit", &twice[..]), ("scroll-down", &down[..])]).is_ok());
+    }
+}
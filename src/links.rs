@@ -0,0 +1,143 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkTarget {
+    Fragment(String),
+    Document {
+        path: PathBuf,
+        fragment: Option<String>,
+    },
+    External(String),
+}
+
+pub fn resolve(base_dir: &Path, destination: &str) -> LinkTarget {
+    let destination = destination.trim();
+    if let Some(fragment) = destination.strip_prefix('#') {
+        return LinkTarget::Fragment(fragment.to_string());
+    }
+    if has_scheme(destination) {
+        return LinkTarget::External(destination.to_string());
+    }
+    let (path_part, fragment) = match destination.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment.to_string())),
+        None => (destination, None),
+    };
+    let path_part = path_part.split('?').next().unwrap_or(path_part);
+    let path = base_dir.join(path_part);
+    if is_markdown(&path) {
+        LinkTarget::Document { path, fragment }
+    } else {
+        LinkTarget::External(path.to_string_lossy().into_owned())
+    }
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "md" | "markdown" | "mdown" | "mkd"
+            )
+        })
+        .unwrap_or(false)
+}
+
+fn has_scheme(destination: &str) -> bool {
+    let Some((scheme, _)) = destination.split_once(':') else {
+        return false;
+    };
+    scheme.len() > 1
+        && scheme
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'))
+}
+
+pub fn heading_slug(title: &str) -> String {
+    title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|ch| {
+            if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+                Some(ch)
+            } else if ch.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Hands `target` to the system opener. Nothing goes through a shell, so
+/// characters such as `&` in a URL are never run as commands.
+pub fn open_external(target: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(target);
+        command
+    } else if cfg!(windows) {
+        let mut command = Command::new("rundll32");
+        command.args(["url.dll,FileProtocolHandler", target]);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(target);
+        command
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Openers exit once the browser is launched; reap them off the UI thread
+    // so each opened link doesn't leave a zombie behind.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_link_kinds() {
+        let base = Path::new("docs");
+        assert_eq!(
+            resolve(base, "#getting-started"),
+            LinkTarget::Fragment(String::from("getting-started"))
+        );
+        assert_eq!(
+            resolve(base, "https://ratatui.rs"),
+            LinkTarget::External(String::from("https://ratatui.rs"))
+        );
+        assert_eq!(
+            resolve(base, "mailto:dev@example.com"),
+            LinkTarget::External(String::from("mailto:dev@example.com"))
+        );
+        assert_eq!(
+            resolve(base, "guide/intro.md#setup"),
+            LinkTarget::Document {
+                path: PathBuf::from("docs/guide/intro.md"),
+                fragment: Some(String::from("setup")),
+            }
+        );
+        assert_eq!(
+            resolve(base, "diagram.png"),
+            LinkTarget::External(String::from("docs/diagram.png"))
+        );
+    }
+
+    #[test]
+    fn slugs_match_common_anchor_style() {
+        assert_eq!(heading_slug("Getting Started"), "getting-started");
+        assert_eq!(heading_slug("Footnotes & Tables"), "footnotes--tables");
+        assert_eq!(heading_slug("`--dump` mode"), "--dump-mode");
+    }
+}
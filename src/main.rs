@@ -1,108 +1,324 @@
+mod anchor;
 mod app;
+mod clipboard;
+mod color;
+mod config;
+mod graphics;
+mod highlight;
+mod history;
+mod keymap;
+mod links;
 mod markdown;
+mod marks;
+mod outline;
+mod search;
+mod state;
+mod theme;
 
 use std::{
     collections::{HashMap, HashSet},
-    env, fs,
-    io::{self, stdout, Write},
+    env,
+    io::{self, stdout, IsTerminal, Write},
     mem,
     path::{Path, PathBuf},
     time::Duration,
 };
 
 use app::App;
+use clipboard::Clipboard;
+use color::{ColorDepth, COLOR_DEPTH_NAMES};
+use config::Config;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, KeyboardEnhancementFlags, MouseButton, MouseEvent, MouseEventKind,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use markdown::{
-    heading_block_colors, markdown_to_render_with_options, CodeBlockOverlay, MarkdownOptions,
-    CODE_BLOCK_BG, CODE_BLOCK_BORDER_FG,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
+use graphics::{Protocol, IMAGE_MODE_NAMES};
+use keymap::{Action, Keymap};
+use markdown::{markdown_to_render_with_options, CodeBlockOverlay, ImageOverlay, MarkdownOptions};
+use pulldown_cmark::HeadingLevel;
 use ratatui::{
     backend::CrosstermBackend,
     style::{Color, Modifier, Style},
     text::Line,
     Terminal,
 };
+use theme::{Theme, THEME_NAMES};
+
+/// Upper bound for count prefixes so `99999999Space` cannot stall the UI.
+const MAX_REPEAT: usize = 10_000;
+
+const USAGE: &str = "Usage: md-viewer [--dump] [--watch] [--mouse] [--no-restore] [--theme NAME] [--color MODE] [--images MODE] [--config PATH] [--help] <path-to-markdown>...";
 
 fn main() -> io::Result<()> {
     let args = parse_args().unwrap_or_else(|| {
-        eprintln!("Usage: md-viewer [--dump] [--help] <path-to-markdown>");
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    });
+    let config = config::load(args.config.as_deref()).unwrap_or_else(|err| {
+        eprintln!("Invalid config: {err}");
+        std::process::exit(2);
+    });
+    let settings = Settings::resolve(&args, config).unwrap_or_else(|err| {
+        eprintln!("{err}");
         std::process::exit(2);
     });
 
     if args.dump {
-        dump_file(&args.path)?;
+        for path in &args.paths {
+            dump_file(path, &settings)?;
+        }
         return Ok(());
     }
 
-    let mut app = App::load(&args.path)?;
+    let mut paths = args.paths.into_iter();
+    let first = paths.next().expect("parse_args requires a path");
+    let positions = state::Positions::load(!args.no_restore);
+    let mut app = App::load(&first, settings.theme, positions)?;
+    app.set_watch(settings.watch);
+    app.set_mouse(settings.mouse);
+    app.set_color_depth(settings.color);
+    app.set_wrap_width(settings.wrap_width);
+    app.set_table_width_limit(settings.max_table_width);
+    app.set_heading_bands(settings.heading_bands);
+    app.set_keymap(settings.keymap);
+    app.set_images(settings.images);
+    app.set_clipboard(settings.clipboard);
+    if settings.outline {
+        app.show_outline();
+    }
+    app.queue_documents(paths.collect());
 
+    // Piped markdown leaves stdin at its end; crossterm then takes keys
+    // from /dev/tty, so make sure there is one before starting the UI.
+    #[cfg(unix)]
+    if !io::stdin().is_terminal() {
+        if let Err(err) = std::fs::File::open("/dev/tty") {
+            eprintln!(
+                "Cannot read keys: stdin is not a terminal and /dev/tty is unavailable ({err})"
+            );
+            std::process::exit(1);
+        }
+    }
     enable_raw_mode()?;
     let mut stdout = stdout();
     execute!(stdout, EnterAlternateScreen)?;
+    // Lets terminals that support it report Ctrl-I apart from Tab.
+    let enhanced_keys = supports_keyboard_enhancement().unwrap_or(false);
+    if enhanced_keys {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let result = run(&mut terminal, &mut app);
 
+    app.clear_graphics(terminal.backend_mut())?;
+    if enhanced_keys {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
+    if app.is_mouse_enabled() {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
+    if let Err(err) = app.save_positions() {
+        eprintln!("Could not save the reading position: {err}");
+    }
 
     result
 }
 
 fn parse_args() -> Option<Args> {
-    let mut dump = false;
-    let mut path = None;
-    for arg in env::args().skip(1) {
+    let mut parsed = Args::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some((flag, value)) = arg.split_once('=') {
+            match flag {
+                "--theme" => parsed.theme = Some(value.to_string()),
+                "--color" => parsed.color = Some(value.to_string()),
+                "--images" => parsed.images = Some(value.to_string()),
+                "--config" => parsed.config = Some(PathBuf::from(value)),
+                _ => parsed.paths.push(PathBuf::from(arg)),
+            }
+            continue;
+        }
         match arg.as_str() {
             "--help" | "-h" => {
                 print_help();
                 return None;
             }
-            "--dump" => dump = true,
-            _ if path.is_none() => path = Some(PathBuf::from(arg)),
-            _ => {
-                path = Some(PathBuf::from(arg));
-                break;
-            }
+            "--dump" => parsed.dump = true,
+            "--watch" | "-w" => parsed.watch = true,
+            "--mouse" => parsed.mouse = true,
+            "--no-restore" => parsed.no_restore = true,
+            "--theme" => parsed.theme = Some(args.next()?),
+            "--color" => parsed.color = Some(args.next()?),
+            "--images" => parsed.images = Some(args.next()?),
+            "--config" => parsed.config = Some(PathBuf::from(args.next()?)),
+            _ => parsed.paths.push(PathBuf::from(arg)),
         }
     }
-    path.map(|path| Args { path, dump })
+    if parsed.paths.is_empty() {
+        if io::stdin().is_terminal() {
+            return None;
+        }
+        parsed.paths.push(PathBuf::from("-"));
+    }
+    Some(parsed)
 }
 
 fn print_help() {
     println!("md-viewer");
-    println!("Usage: md-viewer [--dump] <path-to-markdown>\n");
+    println!("{USAGE}\n");
+    println!("Use - as the path, or pipe input with no path, to read from stdin.");
+    println!("Extra paths are queued as forward history; Alt+Right opens the next one.\n");
     println!("Options:");
-    println!("  --dump       Render the file as ANSI text instead of launching the TUI");
-    println!("  --help, -h   Show this help text");
+    println!("  --dump         Render the file as ANSI text instead of launching the TUI");
+    println!("  --watch, -w    Reload automatically when the file changes on disk");
+    println!("  --mouse        Enable wheel scrolling and clicking (toggle with Alt+M)");
+    println!("  --no-restore   Start at the top instead of where the file was last read");
+    println!(
+        "  --theme NAME   Color theme: {} (default dark)",
+        THEME_NAMES.join(", ")
+    );
+    println!(
+        "  --color MODE   Color depth: {} (NO_COLOR is honored by auto)",
+        COLOR_DEPTH_NAMES.join(", ")
+    );
+    println!(
+        "  --images MODE  Inline images: {} (default auto)",
+        IMAGE_MODE_NAMES.join(", ")
+    );
+    println!("  --config PATH  Read settings from PATH instead of the default config file");
+    println!("  --help, -h     Show this help text");
+    if let Some(path) = config::default_path() {
+        println!("\nDefault config file: {}", path.display());
+    }
 }
 
+#[derive(Default)]
 struct Args {
-    path: PathBuf,
+    paths: Vec<PathBuf>,
     dump: bool,
+    watch: bool,
+    mouse: bool,
+    no_restore: bool,
+    theme: Option<String>,
+    color: Option<String>,
+    images: Option<String>,
+    config: Option<PathBuf>,
+}
+
+/// Command-line flags merged over the config file.
+struct Settings {
+    theme: Theme,
+    color: ColorDepth,
+    watch: bool,
+    mouse: bool,
+    outline: bool,
+    wrap_width: Option<usize>,
+    max_table_width: Option<usize>,
+    heading_bands: bool,
+    images: Option<Protocol>,
+    clipboard: Clipboard,
+    keymap: Keymap,
+}
+
+impl Settings {
+    fn resolve(args: &Args, config: Config) -> Result<Self, String> {
+        let theme = match args.theme.as_deref().or(config.theme.as_deref()) {
+            Some(name) => Theme::by_name(name).ok_or_else(|| {
+                format!(
+                    "Unknown theme '{name}' (available: {})",
+                    THEME_NAMES.join(", ")
+                )
+            })?,
+            None => Theme::default(),
+        };
+        let color = match args.color.as_deref().or(config.color.as_deref()) {
+            Some(mode) => ColorDepth::parse(mode)?,
+            None => None,
+        };
+        let color = color.unwrap_or_else(ColorDepth::detect);
+        let images = match args.images.as_deref().or(config.images.as_deref()) {
+            Some(mode) => Protocol::parse(mode)?,
+            None => Protocol::detect(),
+        };
+        // Half blocks are nothing but colors.
+        let images = images
+            .filter(|&protocol| !(protocol == Protocol::HalfBlocks && color == ColorDepth::Never));
+        let clipboard = match config.clipboard.as_deref() {
+            Some(value) => Clipboard::parse(value)?,
+            None => Clipboard::default(),
+        };
+        let keymap = Keymap::with_overrides(config.key_overrides())
+            .map_err(|err| format!("Invalid [keys] binding: {err}"))?;
+        Ok(Self {
+            theme,
+            color,
+            watch: args.watch || config.defaults.watch,
+            mouse: args.mouse || config.defaults.mouse,
+            outline: config.defaults.outline,
+            wrap_width: config.wrap_width.filter(|&width| width > 0),
+            max_table_width: config.max_table_width.filter(|&width| width > 0),
+            heading_bands: config.heading_bands.unwrap_or(true),
+            images,
+            clipboard,
+            keymap,
+        })
+    }
 }
 
 fn run(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     app: &mut App,
 ) -> io::Result<()> {
+    let mut mouse_captured = false;
     loop {
+        if app.is_mouse_enabled() != mouse_captured {
+            mouse_captured = app.is_mouse_enabled();
+            if mouse_captured {
+                execute!(terminal.backend_mut(), EnableMouseCapture)?;
+            } else {
+                execute!(terminal.backend_mut(), DisableMouseCapture)?;
+            }
+        }
+        app.poll_file_changes();
+        app.poll_clipboard();
         terminal.draw(|frame| app.draw(frame))?;
+        if app.graphics_need_clear() {
+            // Sixel images are not erased by redrawing the cells beneath.
+            terminal.clear()?;
+            terminal.draw(|frame| app.draw(frame))?;
+        }
+        app.flush_graphics(terminal.backend_mut())?;
+        app.flush_clipboard(terminal.backend_mut())?;
 
         if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind != KeyEventKind::Press {
-                    continue;
-                }
-                if handle_key(app, key)? {
-                    break;
+            match event::read()? {
+                Event::Key(key) => {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    if handle_key(app, key)? {
+                        break;
+                    }
                 }
+                Event::Mouse(mouse) => handle_mouse(app, mouse),
+                Event::Resize(..) => app.forget_shown_graphics(),
+                _ => {}
             }
         }
     }
@@ -110,57 +326,233 @@ fn run(
     Ok(())
 }
 
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    if app.is_help_open() || app.is_search_input_active() {
+        return;
+    }
+    if app.is_zoomed() {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => app.zoom_move(-3, 0),
+            MouseEventKind::ScrollDown => app.zoom_move(3, 0),
+            _ => {}
+        }
+        return;
+    }
+    match mouse.kind {
+        MouseEventKind::ScrollUp => app.scroll_up(3),
+        MouseEventKind::ScrollDown => app.scroll_down(3),
+        MouseEventKind::Down(MouseButton::Left) => app.mouse_down(mouse.column, mouse.row),
+        MouseEventKind::Drag(MouseButton::Left) => app.mouse_drag(mouse.row),
+        MouseEventKind::Up(MouseButton::Left) => app.mouse_up(),
+        _ => {}
+    }
+}
+
 fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
     if app.is_help_open() {
+        if let Some((Action::ToggleHelp | Action::Cancel, _)) = app.resolve_key(&key) {
+            app.toggle_help();
+        }
+        return Ok(false);
+    }
+    if app.is_search_input_active() {
         match key.code {
-            KeyCode::Char('?') | KeyCode::Esc => app.toggle_help(),
+            KeyCode::Esc => app.cancel_search(),
+            KeyCode::Enter => app.confirm_search(),
+            KeyCode::Backspace => app.pop_search_char(),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.cancel_search()
+            }
+            KeyCode::Char(ch) => app.push_search_char(ch),
+            _ => {}
+        }
+        return Ok(false);
+    }
+    if app.is_mark_prompt_active() {
+        app.finish_mark_prompt(&key);
+        return Ok(false);
+    }
+    let Some((action, count)) = app.resolve_key(&key) else {
+        return Ok(false);
+    };
+    let times = count.unwrap_or(1).min(MAX_REPEAT);
+    if app.is_zoomed() {
+        let steps = times as isize;
+        let page = app.zoom_page_rows() as isize;
+        let sideways = steps * app::SIDEWAYS_STEP as isize;
+        match action {
+            Action::Open | Action::Cancel | Action::Quit => app.close_zoom(),
+            Action::ScrollDown => app.zoom_move(steps, 0),
+            Action::ScrollUp => app.zoom_move(-steps, 0),
+            Action::PageDown => app.zoom_move(steps * page, 0),
+            Action::PageUp => app.zoom_move(-steps * page, 0),
+            Action::HalfPageDown => app.zoom_move(steps * (page / 2).max(1), 0),
+            Action::HalfPageUp => app.zoom_move(-steps * (page / 2).max(1), 0),
+            Action::ScrollRight => app.zoom_move(0, sideways),
+            Action::ScrollLeft => app.zoom_move(0, -sideways),
+            Action::Top => app.zoom_move(isize::MIN, 0),
+            Action::Bottom => app.zoom_move(isize::MAX, 0),
+            Action::ToggleHelp => app.toggle_help(),
+            Action::CopyCode => app.copy_code_block(),
+            _ => {}
+        }
+        return Ok(false);
+    }
+    if app.is_outline_focused() {
+        let steps = times as isize;
+        match action {
+            Action::Quit => return Ok(true),
+            Action::ScrollUp => app.outline_move(-steps),
+            Action::ScrollDown => app.outline_move(steps),
+            Action::PageUp => app.outline_move(-10 * steps),
+            Action::PageDown => app.outline_move(10 * steps),
+            Action::Top => app.outline_select_edge(false),
+            Action::Bottom => app.outline_select_edge(true),
+            Action::Open => {
+                app.record_jump();
+                app.outline_jump();
+            }
+            Action::Cancel => app.unfocus_outline(),
+            Action::ToggleOutline => app.toggle_outline(),
+            Action::ToggleHelp => app.toggle_help(),
             _ => {}
         }
         return Ok(false);
     }
-    match key.code {
-        KeyCode::Char('q') => return Ok(true),
-        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(true),
-        KeyCode::Up | KeyCode::Char('k') => app.scroll_up(1),
-        KeyCode::Down | KeyCode::Char('j') => app.scroll_down(1),
-        KeyCode::PageUp | KeyCode::Char('p') => app.page_up(),
-        KeyCode::PageDown | KeyCode::Char('n') => app.page_down(),
-        KeyCode::Char(' ') => app.page_down(),
-        KeyCode::Home | KeyCode::Char('g') => app.scroll_to(0),
-        KeyCode::End | KeyCode::Char('G') => app.scroll_to_end(),
-        KeyCode::Char('r') => match app.reload() {
+    let before_jump = is_jump(action).then(|| app.capture_anchor());
+    match action {
+        Action::Quit => return Ok(true),
+        Action::ScrollUp => app.scroll_up(times),
+        Action::ScrollDown => app.scroll_down(times),
+        Action::ScrollLeft => app.scroll_left(times),
+        Action::ScrollRight => app.scroll_right(times),
+        Action::ViewUp => app.view_up(times),
+        Action::ViewDown => app.view_down(times),
+        Action::PageUp => (0..times).for_each(|_| app.page_up()),
+        Action::PageDown => (0..times).for_each(|_| app.page_down()),
+        Action::HalfPageUp => app.half_page_up(count),
+        Action::HalfPageDown => app.half_page_down(count),
+        Action::Top | Action::Bottom if count.is_some() => app.goto_source_line(count.unwrap_or(1)),
+        Action::Top => {
+            app.scroll_to(0);
+            app.screen_top(1);
+        }
+        Action::Bottom => {
+            app.scroll_to_end();
+            app.screen_bottom(1);
+        }
+        Action::Percent => {
+            if let Some(percent) = count {
+                app.scroll_to_percent(percent);
+            }
+        }
+        Action::ScreenTop => app.screen_top(times),
+        Action::ScreenMiddle => app.screen_middle(),
+        Action::ScreenBottom => app.screen_bottom(times),
+        Action::SetMark | Action::JumpMark => app.begin_mark_prompt(action),
+        Action::JumpBack => (0..times).for_each(|_| app.jump_back()),
+        Action::JumpForward => (0..times).for_each(|_| app.jump_forward()),
+        Action::Reload if app.is_stdin() => app.set_status("Reading from stdin, nothing to reload"),
+        Action::Reload => match app.reload() {
             Ok(()) => app.set_status("Reloaded file"),
             Err(err) => app.set_status(format!("Reload failed: {err}")),
         },
-        KeyCode::Char('?') => app.toggle_help(),
-        _ => {}
+        Action::ToggleWatch => app.toggle_watch(),
+        Action::ToggleWrap => app.toggle_wrap(),
+        Action::CopyCode => app.copy_code_block(),
+        Action::Select => app.toggle_selection(),
+        Action::Yank => app.yank(false),
+        Action::YankSource => app.yank(true),
+        Action::ToggleMouse => app.toggle_mouse(),
+        Action::ToggleOutline => app.toggle_outline(),
+        Action::NextLink => (0..times).for_each(|_| app.next_link()),
+        Action::PrevLink => (0..times).for_each(|_| app.prev_link()),
+        Action::Open if app.has_focused_link() => app.follow_link(),
+        Action::Open => app.zoom_block(),
+        Action::Cancel if app.is_selecting() => app.cancel_selection(),
+        Action::Cancel => app.clear_link_focus(),
+        Action::Back => (0..times).for_each(|_| app.go_back()),
+        Action::Forward => (0..times).for_each(|_| app.go_forward()),
+        Action::NextHeading => (0..times).for_each(|_| app.next_heading(HeadingLevel::H6)),
+        Action::PrevHeading => (0..times).for_each(|_| app.prev_heading(HeadingLevel::H6)),
+        Action::NextSection => (0..times).for_each(|_| app.next_heading(HeadingLevel::H2)),
+        Action::PrevSection => (0..times).for_each(|_| app.prev_heading(HeadingLevel::H2)),
+        Action::Search => app.begin_search(),
+        Action::NextMatch => (0..times).for_each(|_| app.next_match()),
+        Action::PrevMatch => (0..times).for_each(|_| app.prev_match()),
+        Action::ToggleHelp => app.toggle_help(),
+    }
+    if let Some(here) = before_jump {
+        app.record_jump_from(here);
     }
 
     Ok(false)
 }
 
-fn dump_file(path: &Path) -> io::Result<()> {
-    let markdown = fs::read_to_string(path)?;
-    let term_width = crossterm::terminal::size()
+/// Moves that land far from where they started and so belong in the jump list.
+fn is_jump(action: Action) -> bool {
+    matches!(
+        action,
+        Action::Top
+            | Action::Bottom
+            | Action::Percent
+            | Action::NextHeading
+            | Action::PrevHeading
+            | Action::NextSection
+            | Action::PrevSection
+            | Action::NextMatch
+            | Action::PrevMatch
+    )
+}
+
+fn dump_file(path: &Path, settings: &Settings) -> io::Result<()> {
+    let markdown = app::read_markdown(path)?;
+    let depth = settings.color;
+    let theme = &settings.theme.map_colors(|color| depth.downsample(color));
+    let mut term_width = crossterm::terminal::size()
         .map(|(w, _)| w as usize)
         .unwrap_or(80)
         .max(1);
+    if let Some(wrap_width) = settings.wrap_width {
+        term_width = term_width.min(wrap_width);
+    }
     let options = MarkdownOptions {
-        max_table_width: term_width,
+        max_table_width: settings
+            .max_table_width
+            .map_or(term_width, |limit| term_width.min(limit)),
+        theme: *theme,
+        // Only half blocks can be written to a plain stream.
+        image_dir: settings.images.map(|_| match path.parent() {
+            Some(dir) if path != Path::new("-") => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        }),
+        ..MarkdownOptions::default()
     };
     let render = markdown_to_render_with_options(&markdown, options);
     let mut heading_bg = HashMap::new();
-    for heading in &render.headings {
-        let (bg, _) = heading_block_colors(heading.level);
+    let banded_headings = if settings.heading_bands {
+        render.headings.as_slice()
+    } else {
+        &[]
+    };
+    for heading in banded_headings {
+        let (bg, _) = theme.heading_band(heading.level);
         heading_bg.insert(heading.line, bg);
     }
     let rule_lines: HashSet<usize> = render.rules.iter().copied().collect();
     let mut out = io::BufWriter::new(io::stdout());
     let mut idx = 0usize;
     let mut code_iter = render.code_blocks.iter().peekable();
+    let mut image_iter = render.images.iter().peekable();
     while idx < render.lines.len() {
+        if let Some(image) = image_iter.next_if(|image| image.line == idx) {
+            let end = (idx + image.rows).min(render.lines.len());
+            write_image_dump(&mut out, image, &render.lines[idx..end], depth)?;
+            idx += image.rows;
+            continue;
+        }
         if rule_lines.contains(&idx) {
-            write_rule_line_dump(&mut out, term_width)?;
+            write_rule_line_dump(&mut out, theme, term_width)?;
             idx += 1;
             continue;
         }
@@ -168,7 +560,7 @@ fn dump_file(path: &Path) -> io::Result<()> {
             if idx == block.line_start {
                 let end = block.line_end.min(render.lines.len());
                 let slice = &render.lines[block.line_start..end];
-                write_code_block_dump(&mut out, slice, block, term_width)?;
+                write_code_block_dump(&mut out, theme, slice, block, term_width)?;
                 idx = block.line_end;
                 code_iter.next();
                 continue;
@@ -184,6 +576,30 @@ fn dump_file(path: &Path) -> io::Result<()> {
 
 const ANSI_RESET: &str = "\x1b[0m";
 
+/// `prefixes` are the reserved lines, holding quote bars and list indent.
+fn write_image_dump(
+    out: &mut impl Write,
+    image: &ImageOverlay,
+    prefixes: &[Line<'_>],
+    depth: ColorDepth,
+) -> io::Result<()> {
+    let rows = graphics::half_block_rows(&image.path, image.cols, image.rows);
+    for idx in 0..image.rows {
+        if let Some(prefix) = prefixes.get(idx) {
+            write_line_content(out, prefix, None)?;
+        }
+        let row = rows.as_ref().and_then(|rows| rows.get(idx));
+        for &(upper, lower) in row.into_iter().flatten() {
+            let (symbol, fg, bg) =
+                graphics::half_block(depth.downsample(upper), depth.downsample(lower));
+            let style = Style::default().fg(fg).bg(bg);
+            write!(out, "{}{symbol}{}", style_prefix(style, None), ANSI_RESET)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
 fn write_regular_line_dump(
     out: &mut impl Write,
     line: &Line<'_>,
@@ -207,9 +623,9 @@ fn write_regular_line_dump(
     writeln!(out)
 }
 
-fn write_rule_line_dump(out: &mut impl Write, term_width: usize) -> io::Result<()> {
+fn write_rule_line_dump(out: &mut impl Write, theme: &Theme, term_width: usize) -> io::Result<()> {
     let width = term_width.max(1);
-    let style = Style::default().fg(Color::DarkGray);
+    let style = Style::default().fg(theme.rule);
     write!(
         out,
         "{}{}{}",
@@ -222,6 +638,7 @@ fn write_rule_line_dump(out: &mut impl Write, term_width: usize) -> io::Result<(
 
 fn write_code_block_dump(
     out: &mut impl Write,
+    theme: &Theme,
     lines: &[Line<'_>],
     block: &CodeBlockOverlay,
     term_width: usize,
@@ -249,27 +666,28 @@ fn write_code_block_dump(
         .unwrap_or(0)
         .max(1);
     let inner_width = content_width + 2;
-    write_code_block_border(out, block.language.as_deref(), inner_width, true)?;
-    let border_style = code_block_border_style();
+    write_code_block_border(out, theme, block.language.as_deref(), inner_width, true)?;
+    let border_style = theme.code_border();
+    let code_bg = Some(theme.code_bg);
     for row in &rows {
         write!(
             out,
             "{}│ {}",
-            style_prefix(border_style, Some(CODE_BLOCK_BG)),
+            style_prefix(border_style, code_bg),
             ANSI_RESET
         )?;
         let rendered = write_segments(
             out,
             row.iter().map(|(style, text)| (*style, text.as_str())),
-            Some(CODE_BLOCK_BG),
+            code_bg,
         )?;
         if rendered < content_width {
             let padding = content_width - rendered;
-            let padding_style = Style::default().bg(CODE_BLOCK_BG);
+            let padding_style = Style::default().bg(theme.code_bg);
             write!(
                 out,
                 "{}{}{}",
-                style_prefix(padding_style, Some(CODE_BLOCK_BG)),
+                style_prefix(padding_style, code_bg),
                 " ".repeat(padding),
                 ANSI_RESET
             )?;
@@ -277,16 +695,17 @@ fn write_code_block_dump(
         write!(
             out,
             "{} │{}",
-            style_prefix(border_style, Some(CODE_BLOCK_BG)),
+            style_prefix(border_style, code_bg),
             ANSI_RESET
         )?;
         writeln!(out)?;
     }
-    write_code_block_border(out, None, inner_width, false)
+    write_code_block_border(out, theme, None, inner_width, false)
 }
 
 fn write_code_block_border(
     out: &mut impl Write,
+    theme: &Theme,
     title: Option<&str>,
     inner_width: usize,
     top: bool,
@@ -311,7 +730,7 @@ fn write_code_block_border(
     write!(
         out,
         "{}{}{}",
-        style_prefix(code_block_border_style(), Some(CODE_BLOCK_BG)),
+        style_prefix(theme.code_border(), Some(theme.code_bg)),
         line,
         ANSI_RESET
     )?;
@@ -337,10 +756,12 @@ fn style_prefix(mut style: Style, default_bg: Option<Color>) -> String {
         style.bg = default_bg;
     }
     let mut codes: Vec<String> = Vec::new();
-    if let Some(fg) = style.fg {
+    // Every segment ends with a full reset, so an explicit Reset adds nothing
+    // and only shows up when colors are disabled.
+    if let Some(fg) = style.fg.filter(|&fg| fg != Color::Reset) {
         codes.push(color_code(fg, true));
     }
-    if let Some(bg) = style.bg {
+    if let Some(bg) = style.bg.filter(|&bg| bg != Color::Reset) {
         codes.push(color_code(bg, false));
     }
     let modifiers = style.add_modifier;
@@ -369,13 +790,6 @@ fn style_prefix(mut style: Style, default_bg: Option<Color>) -> String {
     }
 }
 
-fn code_block_border_style() -> Style {
-    Style::default()
-        .fg(CODE_BLOCK_BORDER_FG)
-        .bg(CODE_BLOCK_BG)
-        .add_modifier(Modifier::BOLD)
-}
-
 fn write_segments<'a, I>(
     out: &mut impl Write,
     segments: I,
@@ -1,37 +1,50 @@
-use std::mem;
+use std::{
+    collections::{HashSet, VecDeque},
+    mem,
+    ops::Range,
+    path::PathBuf,
+};
 
 use pulldown_cmark::{Alignment, CodeBlockKind, CowStr, Event as MdEvent, Options, Parser, Tag};
 use ratatui::{
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
 };
 use textwrap::{wrap, Options as WrapOptions};
-use unicode_width::UnicodeWidthStr;
+use unicode_width::UnicodeWidthChar;
+
+use crate::graphics::{self, ImageSizes};
+use crate::highlight::Highlighter;
+use crate::theme::Theme;
 
-pub const CODE_BLOCK_FG: Color = Color::Rgb(225, 228, 235);
-pub const CODE_BLOCK_BG: Color = Color::Rgb(12, 16, 26);
-pub const CODE_BLOCK_BORDER_FG: Color = Color::Rgb(150, 160, 175);
 const MIN_COLUMN_WIDTH: usize = 3;
-const BLOCKQUOTE_COLORS: [Color; 4] = [
-    Color::Rgb(255, 200, 150),
-    Color::Rgb(230, 170, 120),
-    Color::Rgb(200, 140, 110),
-    Color::Rgb(170, 120, 100),
-];
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct MarkdownOptions {
     pub max_table_width: usize,
+    pub theme: Theme,
+    /// Directory local images are resolved against; `None` keeps images as
+    /// text placeholders.
+    pub image_dir: Option<PathBuf>,
+    pub image_sizes: ImageSizes,
+    /// Tables (by position in the document) laid out at their natural width
+    /// instead of being squeezed into `max_table_width`.
+    pub no_wrap_tables: HashSet<usize>,
 }
 
 impl Default for MarkdownOptions {
     fn default() -> Self {
         Self {
             max_table_width: 80,
+            theme: Theme::default(),
+            image_dir: None,
+            image_sizes: ImageSizes::default(),
+            no_wrap_tables: HashSet::new(),
         }
     }
 }
 
+#[cfg(test)]
 pub fn markdown_to_render(markdown: &str) -> RenderedMarkdown {
     markdown_to_render_with_options(markdown, MarkdownOptions::default())
 }
@@ -48,41 +61,135 @@ pub fn markdown_to_render_with_options(
             | Options::ENABLE_TASKLISTS
             | Options::ENABLE_FOOTNOTES,
     );
-    for event in parser {
+    for (event, range) in parser.into_offset_iter() {
+        buffer.lines.set_source(range);
         buffer.handle_event(event);
     }
     buffer.finalize()
 }
 
-pub fn heading_block_colors(level: pulldown_cmark::HeadingLevel) -> (Color, Color) {
-    match level {
-        pulldown_cmark::HeadingLevel::H1 => (Color::Rgb(48, 52, 70), Color::Rgb(235, 235, 245)),
-        pulldown_cmark::HeadingLevel::H2 => (Color::Rgb(40, 44, 60), Color::Rgb(225, 225, 235)),
-        pulldown_cmark::HeadingLevel::H3 => (Color::Rgb(35, 39, 54), Color::Rgb(210, 210, 225)),
-        pulldown_cmark::HeadingLevel::H4 => (Color::Rgb(30, 34, 48), Color::Rgb(200, 200, 215)),
-        pulldown_cmark::HeadingLevel::H5 => (Color::Rgb(28, 32, 44), Color::Rgb(190, 190, 205)),
-        pulldown_cmark::HeadingLevel::H6 => (Color::Rgb(24, 28, 38), Color::Rgb(180, 180, 195)),
-    }
-}
-
 pub fn line_row_span(line: &Line<'_>, width: usize) -> u16 {
     if width == 0 {
         return 0;
     }
-    let line_width = line.width();
-    if line_width == 0 {
-        1
-    } else {
-        let rows = (line_width + width - 1) / width;
-        rows.min(u16::MAX as usize) as u16
+    if line.width() <= width {
+        return 1;
     }
+    wrap_rows(line, width).len().min(u16::MAX as usize) as u16
+}
+
+/// How the document's `Paragraph` word-wraps `line` at `width`: for each
+/// row, the byte offset and width of every character drawn on it. This
+/// follows ratatui's `WordWrapper` without trimming, which is not public.
+pub fn wrap_rows(line: &Line<'_>, width: usize) -> Vec<Vec<(usize, usize)>> {
+    let mut rows = Vec::new();
+    let (mut current, mut current_width) = (Vec::new(), 0);
+    let (mut word, mut word_width) = (Vec::new(), 0);
+    let (mut spaces, mut space_width) = (VecDeque::new(), 0);
+    let mut after_word = false;
+    let mut offset = 0;
+    for ch in line.spans.iter().flat_map(|span| span.content.chars()) {
+        let at = offset;
+        offset += ch.len_utf8();
+        let is_space = ch == '\u{200b}' || (ch.is_whitespace() && ch != '\u{a0}');
+        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if ch_width > width {
+            continue;
+        }
+        if after_word && is_space
+            || word_width + space_width + ch_width > width && current.is_empty()
+        {
+            current.extend(spaces.drain(..));
+            current.append(&mut word);
+            current_width += space_width + word_width;
+            (space_width, word_width) = (0, 0);
+        }
+        if current_width >= width
+            || current_width + space_width + word_width >= width && ch_width > 0
+        {
+            let mut remaining = width.saturating_sub(current_width);
+            rows.push(mem::take(&mut current));
+            current_width = 0;
+            // Spaces at the break are dropped, as far as they fit the row.
+            let mut first = spaces.pop_front();
+            while let Some((_, space)) = first {
+                space_width -= space;
+                if space > remaining {
+                    break;
+                }
+                remaining -= space;
+                first = spaces.pop_front();
+            }
+            if is_space && first.is_none() {
+                continue;
+            }
+        }
+        if is_space {
+            space_width += ch_width;
+            spaces.push_back((at, ch_width));
+        } else {
+            word_width += ch_width;
+            word.push((at, ch_width));
+        }
+        after_word = !is_space;
+    }
+    if !word.is_empty() || !spaces.is_empty() {
+        if current.is_empty() && word.is_empty() {
+            rows.push(Vec::new());
+        } else {
+            current.extend(spaces);
+        }
+        current.append(&mut word);
+    }
+    if !current.is_empty() {
+        rows.push(current);
+    }
+    if rows.is_empty() {
+        rows.push(Vec::new());
+    }
+    rows
+}
+
+/// The columns `skip..skip + width` of `line`; a wide character cut in half
+/// at either edge becomes a space.
+pub fn slice_line(line: &Line<'_>, skip: usize, width: usize) -> Line<'static> {
+    let end = skip.saturating_add(width);
+    let mut column = 0usize;
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for span in &line.spans {
+        let mut content = String::new();
+        for ch in span.content.chars() {
+            let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+            let start = column;
+            column += ch_width;
+            if column <= skip || start >= end {
+                continue;
+            }
+            if start < skip || column > end {
+                content.push_str(&" ".repeat(column.min(end) - start.max(skip)));
+            } else {
+                content.push(ch);
+            }
+        }
+        if !content.is_empty() {
+            spans.push(Span::styled(content, span.style));
+        }
+        if column >= end {
+            break;
+        }
+    }
+    Line::from(spans).style(line.style)
 }
 
 pub struct RenderedMarkdown {
     pub lines: Vec<Line<'static>>,
+    pub line_sources: Vec<Range<usize>>,
     pub headings: Vec<HeadingOverlay>,
     pub code_blocks: Vec<CodeBlockOverlay>,
+    pub tables: Vec<TableOverlay>,
     pub rules: Vec<usize>,
+    pub links: Vec<LinkOverlay>,
+    pub images: Vec<ImageOverlay>,
 }
 
 #[derive(Clone, Copy)]
@@ -91,17 +198,56 @@ pub struct HeadingOverlay {
     pub level: pulldown_cmark::HeadingLevel,
 }
 
+#[derive(Clone)]
+pub struct LinkOverlay {
+    pub destination: String,
+    pub spans: Vec<LinkSpan>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinkSpan {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An image drawn over `rows` blank lines reserved from `line` on.
+#[derive(Clone)]
+pub struct ImageOverlay {
+    pub line: usize,
+    pub rows: usize,
+    pub cols: usize,
+    /// Columns of quote bars and list indent left of the picture.
+    pub offset: usize,
+    pub path: PathBuf,
+}
+
 #[derive(Clone)]
 pub struct CodeBlockOverlay {
     pub line_start: usize,
     pub line_end: usize,
     pub language: Option<String>,
+    /// The code as written, without the rendered padding and prefixes.
+    pub source: String,
+    /// Byte range of the whole block in the markdown, fences included.
+    pub source_range: Range<usize>,
+}
+
+#[derive(Clone, Copy)]
+pub struct TableOverlay {
+    pub line_start: usize,
+    pub line_end: usize,
 }
 
 struct LineWriter {
     lines: Vec<Line<'static>>,
+    sources: Vec<Range<usize>>,
+    source: Range<usize>,
+    current_source: Option<Range<usize>>,
     current: Vec<Span<'static>>,
     line_start: bool,
+    /// Whether the current line holds content beyond bullets and prefixes.
+    has_text: bool,
     last_blank: bool,
     pending_heading: Option<pulldown_cmark::HeadingLevel>,
     heading_overlays: Vec<HeadingOverlay>,
@@ -111,8 +257,12 @@ impl Default for LineWriter {
     fn default() -> Self {
         Self {
             lines: Vec::new(),
+            sources: Vec::new(),
+            source: 0..0,
+            current_source: None,
             current: Vec::new(),
             line_start: true,
+            has_text: false,
             last_blank: true,
             pending_heading: None,
             heading_overlays: Vec::new(),
@@ -125,6 +275,15 @@ impl LineWriter {
         self.line_start
     }
 
+    fn has_text(&self) -> bool {
+        self.has_text
+    }
+
+    fn cursor(&self) -> (usize, usize) {
+        let len = self.current.iter().map(|span| span.content.len()).sum();
+        (self.lines.len(), len)
+    }
+
     fn len(&self) -> usize {
         self.lines.len()
     }
@@ -137,10 +296,24 @@ impl LineWriter {
         self.pending_heading = Some(level);
     }
 
+    fn set_source(&mut self, range: Range<usize>) {
+        self.source = range;
+    }
+
+    fn push_line(&mut self, line: Line<'static>, source: Range<usize>) {
+        self.lines.push(line);
+        self.sources.push(source);
+    }
+
     fn push_span(&mut self, span: Span<'static>, mark_content: bool) {
         self.current.push(span);
+        self.current_source = Some(match self.current_source.take() {
+            Some(range) => range.start.min(self.source.start)..range.end.max(self.source.end),
+            None => self.source.clone(),
+        });
         if mark_content {
             self.last_blank = false;
+            self.has_text = true;
         }
         self.line_start = false;
     }
@@ -148,12 +321,16 @@ impl LineWriter {
     fn flush_line(&mut self, allow_empty: bool) {
         if self.current.is_empty() {
             if allow_empty {
-                self.lines.push(Line::default());
+                self.push_line(Line::default(), self.source.clone());
                 self.last_blank = true;
             }
         } else {
             let spans = mem::take(&mut self.current);
-            self.lines.push(Line::from(spans));
+            let source = self
+                .current_source
+                .take()
+                .unwrap_or_else(|| self.source.clone());
+            self.push_line(Line::from(spans), source);
             self.last_blank = false;
             if let Some(level) = self.pending_heading.take() {
                 let line_index = self.lines.len().saturating_sub(1);
@@ -164,11 +341,12 @@ impl LineWriter {
             }
         }
         self.line_start = true;
+        self.has_text = false;
     }
 
     fn ensure_block_gap(&mut self) {
         if !self.is_empty() && !self.last_blank {
-            self.lines.push(Line::default());
+            self.push_line(Line::default(), self.source.clone());
             self.last_blank = true;
         }
         self.line_start = true;
@@ -176,7 +354,7 @@ impl LineWriter {
 
     fn push_blank_line(&mut self) {
         if !self.last_blank {
-            self.lines.push(Line::default());
+            self.push_line(Line::default(), self.source.clone());
             self.last_blank = true;
         }
         self.line_start = true;
@@ -184,7 +362,7 @@ impl LineWriter {
 
     fn push_manual_line(&mut self, line: Line<'static>) -> usize {
         let idx = self.lines.len();
-        self.lines.push(line);
+        self.push_line(line, self.source.clone());
         self.last_blank = self.lines[idx].spans.is_empty();
         self.line_start = true;
         idx
@@ -198,7 +376,10 @@ impl LineWriter {
         if new_lines.is_empty() {
             return;
         }
+        let count = new_lines.len();
         self.lines.extend(new_lines);
+        self.sources
+            .extend(std::iter::repeat_n(self.source.clone(), count));
         self.last_blank = self
             .lines
             .last()
@@ -207,12 +388,25 @@ impl LineWriter {
         self.line_start = true;
     }
 
-    fn finalize(mut self) -> (Vec<Line<'static>>, Vec<HeadingOverlay>) {
+    fn finalize(mut self) -> (Vec<Line<'static>>, Vec<Range<usize>>, Vec<HeadingOverlay>) {
         if !self.current.is_empty() {
             let spans = mem::take(&mut self.current);
-            self.lines.push(Line::from(spans));
+            let source = self
+                .current_source
+                .take()
+                .unwrap_or_else(|| self.source.clone());
+            self.push_line(Line::from(spans), source);
+        }
+        // Closing events report the range of the whole block, so trailing blank
+        // lines would otherwise point back at its start. Keep the map monotonic.
+        let mut floor = 0usize;
+        for range in &mut self.sources {
+            if range.start < floor {
+                *range = floor..range.end.max(floor);
+            }
+            floor = range.start;
         }
-        (self.lines, self.heading_overlays)
+        (self.lines, self.sources, self.heading_overlays)
     }
 }
 
@@ -220,6 +414,10 @@ impl LineWriter {
 struct CodeBlockState {
     start_line: Option<usize>,
     language: Option<String>,
+    highlighter: Option<Highlighter>,
+    raw: String,
+    /// Byte offset of the opening fence.
+    source_start: usize,
 }
 
 impl CodeBlockState {
@@ -229,13 +427,17 @@ impl CodeBlockState {
 
     fn start(&mut self, start_line: usize, language: Option<String>) {
         self.start_line = Some(start_line);
+        self.highlighter = language.as_deref().and_then(Highlighter::for_info);
         self.language = language;
+        self.raw.clear();
     }
 
-    fn take(&mut self) -> Option<(usize, Option<String>)> {
+    fn take(&mut self) -> Option<(usize, Option<String>, String)> {
+        self.highlighter = None;
+        let raw = mem::take(&mut self.raw);
         self.start_line
             .take()
-            .map(|start| (start, self.language.take()))
+            .map(|start| (start, self.language.take(), raw))
     }
 }
 
@@ -245,8 +447,16 @@ struct MarkdownBuffer {
     list_stack: Vec<ListState>,
     blockquote_depth: usize,
     table: Option<TableBuilder>,
+    active_link: Option<LinkOverlay>,
+    links: Vec<LinkOverlay>,
     code_blocks: Vec<CodeBlockOverlay>,
+    tables: Vec<TableOverlay>,
     rule_lines: Vec<usize>,
+    images: Vec<ImageOverlay>,
+    image: Option<ImageLabel>,
+    /// Set after an image label ends a line so the text that follows does
+    /// not start with the space that separated it from the image.
+    trim_next_text: bool,
     code_block: CodeBlockState,
     options: MarkdownOptions,
 }
@@ -259,14 +469,56 @@ impl MarkdownBuffer {
             list_stack: Vec::new(),
             blockquote_depth: 0,
             table: None,
+            active_link: None,
+            links: Vec::new(),
             code_blocks: Vec::new(),
+            tables: Vec::new(),
             rule_lines: Vec::new(),
+            images: Vec::new(),
+            image: None,
+            trim_next_text: false,
             code_block: CodeBlockState::default(),
             options,
         }
     }
 }
 
+/// An image whose alt text is still being collected from child events.
+struct ImageLabel {
+    destination: String,
+    title: String,
+    alt: String,
+}
+
+impl ImageLabel {
+    fn alt(&self) -> &str {
+        match self.alt.trim() {
+            "" => "image",
+            alt => alt,
+        }
+    }
+
+    /// `🖼 alt — title (path)`, with the icon and alt text in the theme's
+    /// image color.
+    fn spans(&self, style: Style, theme: &Theme) -> Vec<Span<'static>> {
+        let mut spans = vec![Span::styled(
+            format!("🖼 {}", self.alt()),
+            style.fg(theme.image),
+        )];
+        if !self.title.is_empty() {
+            spans.push(Span::styled(
+                format!(" — {}", self.title),
+                style.add_modifier(Modifier::ITALIC),
+            ));
+        }
+        spans.push(Span::styled(
+            format!(" ({})", self.destination),
+            style.add_modifier(Modifier::DIM),
+        ));
+        spans
+    }
+}
+
 #[derive(Clone, Copy)]
 struct ListState {
     ordered: bool,
@@ -284,9 +536,26 @@ impl Default for ListState {
 
 impl MarkdownBuffer {
     fn handle_event(&mut self, event: MdEvent<'_>) {
+        if let Some(image) = self.image.as_mut() {
+            match event {
+                MdEvent::Text(text) | MdEvent::Code(text) => image.alt.push_str(&text),
+                MdEvent::SoftBreak | MdEvent::HardBreak => image.alt.push(' '),
+                MdEvent::End(Tag::Image(..)) => self.finish_image(),
+                _ => {}
+            }
+            return;
+        }
+        let trim = mem::take(&mut self.trim_next_text);
         match event {
             MdEvent::Start(tag) => self.start_tag(tag),
             MdEvent::End(tag) => self.end_tag(tag),
+            MdEvent::Text(text) if trim && text.starts_with(char::is_whitespace) => {
+                let text = CowStr::from(text.trim_start().to_string());
+                if self.push_table_text(&text) {
+                    return;
+                }
+                self.push_text(text)
+            }
             MdEvent::Text(text) => {
                 if self.push_table_text(&text) {
                     return;
@@ -341,7 +610,12 @@ impl MarkdownBuffer {
             Tag::Table(alignments) => {
                 self.ensure_block_gap();
                 self.flush_line(false);
-                self.table = Some(TableBuilder::new(alignments, self.options.max_table_width));
+                let max_width = if self.options.no_wrap_tables.contains(&self.tables.len()) {
+                    usize::MAX
+                } else {
+                    self.options.max_table_width
+                };
+                self.table = Some(TableBuilder::new(alignments, max_width));
                 return;
             }
             Tag::TableHead => {
@@ -365,15 +639,8 @@ impl MarkdownBuffer {
             _ => {}
         }
 
-        if self.table_cell_active() {
-            match tag {
-                Tag::Emphasis
-                | Tag::Strong
-                | Tag::Strikethrough
-                | Tag::Link(_, _, _)
-                | Tag::Paragraph => return,
-                _ => {}
-            }
+        if self.table_cell_active() && matches!(tag, Tag::Paragraph) {
+            return;
         }
 
         match tag {
@@ -381,14 +648,14 @@ impl MarkdownBuffer {
             Tag::Heading(level, _, _) => {
                 self.ensure_block_gap();
                 self.lines.queue_heading(level);
-                self.push_style(self.heading_text_style(level));
+                self.push_style(self.options.theme.heading_text(level));
             }
             Tag::BlockQuote => {
                 self.ensure_block_gap();
                 self.blockquote_depth += 1;
                 self.push_style(
                     Style::default()
-                        .fg(Color::Gray)
+                        .fg(self.options.theme.blockquote_text)
                         .add_modifier(Modifier::ITALIC),
                 );
             }
@@ -405,20 +672,30 @@ impl MarkdownBuffer {
             Tag::Strikethrough => {
                 self.push_style(self.current_style().add_modifier(Modifier::CROSSED_OUT))
             }
-            Tag::Link(_, _, _) => self.push_style(
-                self.current_style()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::UNDERLINED),
-            ),
+            Tag::Link(_, dest, _) => {
+                match self.table.as_mut().filter(|table| table.is_collecting()) {
+                    // Cell text is laid out when the table ends, which is
+                    // when these links get their spans.
+                    Some(table) => table.start_link(dest.to_string()),
+                    None => {
+                        self.active_link = Some(LinkOverlay {
+                            destination: dest.to_string(),
+                            spans: Vec::new(),
+                        })
+                    }
+                }
+                self.push_style(
+                    self.current_style()
+                        .fg(self.options.theme.link)
+                        .add_modifier(Modifier::UNDERLINED),
+                )
+            }
             Tag::Image(_, dest, title) => {
-                self.ensure_block_gap();
-                let label = if title.is_empty() {
-                    format!("![image]({})", dest)
-                } else {
-                    format!("![{}]({})", title, dest)
-                };
-                self.push_text(label.into());
-                self.soft_break();
+                self.image = Some(ImageLabel {
+                    destination: dest.to_string(),
+                    title: title.to_string(),
+                    alt: String::new(),
+                });
             }
             Tag::FootnoteDefinition(name) => {
                 self.ensure_block_gap();
@@ -452,10 +729,21 @@ impl MarkdownBuffer {
             Tag::Table(_) => {
                 self.flush_line(false);
                 if let Some(table) = self.table.take() {
-                    let mut rendered = table.into_lines();
+                    let (mut rendered, links) = table.into_lines();
                     if rendered.is_empty() {
                         rendered.push(Line::from("(empty table)"));
                     }
+                    let line_start = self.lines.len();
+                    self.links.extend(links.into_iter().map(|mut link| {
+                        for span in &mut link.spans {
+                            span.line += line_start;
+                        }
+                        link
+                    }));
+                    self.tables.push(TableOverlay {
+                        line_start,
+                        line_end: line_start + rendered.len(),
+                    });
                     self.lines.extend_lines(rendered);
                     self.push_blank_line();
                 }
@@ -464,13 +752,6 @@ impl MarkdownBuffer {
             _ => {}
         }
 
-        if self.table_cell_active() {
-            match tag {
-                Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(_, _, _) => return,
-                _ => {}
-            }
-        }
-
         match tag {
             Tag::Paragraph => {
                 self.flush_line(false);
@@ -504,14 +785,100 @@ impl MarkdownBuffer {
                 self.push_blank_line();
                 self.force_blank_line();
             }
-            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(_, _, _) => {
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough => {
                 self.pop_style();
             }
-            Tag::Image(..) | Tag::FootnoteDefinition(_) => {}
+            Tag::Link(_, _, _) => {
+                if let Some(table) = self.table.as_mut() {
+                    table.end_link();
+                }
+                self.finish_link();
+                self.pop_style();
+            }
+            Tag::FootnoteDefinition(_) => {}
             _ => {}
         }
     }
 
+    /// Emits the label of a finished image on its own line, below the
+    /// picture when one can be drawn, or as plain text inside a table cell.
+    fn finish_image(&mut self) {
+        let Some(image) = self.image.take() else {
+            return;
+        };
+        let spans = image.spans(self.current_style(), &self.options.theme);
+        if let Some(table) = self.table.as_mut().filter(|table| table.is_collecting()) {
+            for span in spans {
+                table.push_span(span);
+            }
+            return;
+        }
+        if self.lines.has_text() {
+            self.flush_line(false);
+        }
+        let reserved = self.reserve_image(&image.destination);
+        if self.lines.is_line_start() {
+            self.insert_prefixes();
+            if reserved {
+                // Keeps the label under the picture.
+                self.push_list_indent();
+            }
+        }
+        let start = self.lines.cursor();
+        for span in spans {
+            self.lines.push_span(span, true);
+        }
+        self.extend_link(start);
+        self.flush_line(false);
+        self.trim_next_text = true;
+    }
+
+    /// Reserves lines for a local image that can be decoded, holding only
+    /// quote bars and list indent; the app or `--dump` draws the picture
+    /// to their right. Returns whether anything was reserved.
+    fn reserve_image(&mut self, destination: &str) -> bool {
+        let Some(path) = self
+            .options
+            .image_dir
+            .as_deref()
+            .and_then(|dir| graphics::local_path(dir, destination))
+        else {
+            return false;
+        };
+        let size = self.options.image_sizes.get(&path);
+        let Some((cols, rows)) =
+            size.and_then(|size| graphics::fit(size, self.options.max_table_width))
+        else {
+            return false;
+        };
+        self.flush_line(false);
+        self.ensure_block_gap();
+        let line = self.lines.len();
+        for _ in 0..rows {
+            self.insert_prefixes();
+            self.push_list_indent();
+            self.flush_line(true);
+        }
+        self.lines.mark_last_non_blank();
+        let offset = self.lines.lines.get(line).map_or(0, Line::width);
+        self.images.push(ImageOverlay {
+            line,
+            rows,
+            cols,
+            offset,
+            path,
+        });
+        true
+    }
+
+    /// Lines up with the text of the enclosing list items.
+    fn push_list_indent(&mut self) {
+        let indent = self.list_stack.len() * 2;
+        if indent > 0 {
+            self.lines.push_span(Span::raw(" ".repeat(indent)), false);
+        }
+    }
+
     fn start_code_block(&mut self, kind: CodeBlockKind<'_>) {
         self.ensure_block_gap();
         self.flush_line(false);
@@ -528,7 +895,8 @@ impl MarkdownBuffer {
         };
         let start = self.lines.len();
         self.code_block.start(start, language);
-        self.push_style(Self::code_block_style());
+        self.code_block.source_start = self.lines.source.start;
+        self.push_style(self.options.theme.code_block());
     }
 
     fn start_list_item(&mut self) {
@@ -545,12 +913,13 @@ impl MarkdownBuffer {
                 format!("{}{} ", padding, symbol)
             };
             self.lines.push_span(
-                Span::styled(bullet, Style::default().fg(Color::Gray)),
+                Span::styled(bullet, Style::default().fg(self.options.theme.list_marker)),
                 false,
             );
         } else {
+            let marker_style = Style::default().fg(self.options.theme.list_marker);
             self.lines
-                .push_span(Span::styled("- ", Style::default().fg(Color::Gray)), false);
+                .push_span(Span::styled("- ", marker_style), false);
         }
     }
 
@@ -575,20 +944,25 @@ impl MarkdownBuffer {
         if text.is_empty() {
             return;
         }
+        if self.code_block.is_active() {
+            self.code_block.raw.push_str(&text);
+        }
         if self.code_block.is_active() && text.contains('\n') {
-            let mut buffer = String::new();
-            for ch in text.chars() {
-                if ch == '\n' {
-                    self.push_text_segment(&buffer);
-                    buffer.clear();
+            let event_source = self.lines.source.clone();
+            let verbatim = event_source.len() == text.len();
+            let mut segment_start = 0usize;
+            for (idx, segment) in text.split('\n').enumerate() {
+                if idx > 0 {
                     self.flush_line(true);
-                } else {
-                    buffer.push(ch);
                 }
+                if verbatim {
+                    let start = event_source.start + segment_start;
+                    self.lines.set_source(start..start + segment.len());
+                }
+                self.push_text_segment(segment);
+                segment_start += segment.len() + 1;
             }
-            if !buffer.is_empty() {
-                self.push_text_segment(&buffer);
-            }
+            self.lines.set_source(event_source);
             return;
         }
         self.push_text_segment(text.as_ref());
@@ -605,20 +979,56 @@ impl MarkdownBuffer {
             self.insert_prefixes();
         }
         let style = self.current_style();
+        if let Some(highlighter) = self.code_block.highlighter.as_mut() {
+            let tokens: Vec<Span<'static>> = highlighter
+                .highlight_line(text)
+                .into_iter()
+                .map(|(kind, piece)| {
+                    Span::styled(
+                        piece.to_string(),
+                        style.patch(self.options.theme.token(kind)),
+                    )
+                })
+                .collect();
+            for span in tokens {
+                self.lines.push_span(span, true);
+            }
+            return;
+        }
+        let start = self.lines.cursor();
         self.lines
             .push_span(Span::styled(text.to_string(), style), true);
+        self.extend_link(start);
     }
 
     fn push_code_span(&mut self, text: CowStr<'_>) {
-        let style = self
-            .current_style()
-            .fg(Color::Yellow)
-            .add_modifier(Modifier::DIM);
+        let style = self.current_style().patch(self.options.theme.inline_code);
         if self.lines.is_line_start() {
             self.insert_prefixes();
         }
+        let start = self.lines.cursor();
         self.lines
             .push_span(Span::styled(format!("`{}`", text), style), true);
+        self.extend_link(start);
+    }
+
+    fn extend_link(&mut self, (line, start): (usize, usize)) {
+        let Some(link) = self.active_link.as_mut() else {
+            return;
+        };
+        let (_, end) = self.lines.cursor();
+        match link.spans.last_mut() {
+            Some(last) if last.line == line && last.end == start => last.end = end,
+            _ => link.spans.push(LinkSpan { line, start, end }),
+        }
+    }
+
+    fn finish_link(&mut self) {
+        if let Some(link) = self.active_link.take() {
+            if !link.spans.is_empty() {
+                self.links.push(link);
+            }
+        }
     }
 
     fn table_cell_active(&self) -> bool {
@@ -629,9 +1039,10 @@ impl MarkdownBuffer {
     }
 
     fn push_table_text(&mut self, text: &CowStr<'_>) -> bool {
+        let style = self.current_style();
         if let Some(table) = self.table.as_mut() {
             if table.is_collecting() {
-                table.push_text(text);
+                table.push_text(text, style);
                 return true;
             }
         }
@@ -639,9 +1050,10 @@ impl MarkdownBuffer {
     }
 
     fn push_table_html(&mut self, html: &CowStr<'_>) -> bool {
+        let style = self.current_style();
         if let Some(table) = self.table.as_mut() {
             if table.is_collecting() {
-                table.push_html(html);
+                table.push_html(html, style);
                 return true;
             }
         }
@@ -649,9 +1061,10 @@ impl MarkdownBuffer {
     }
 
     fn push_table_code(&mut self, text: &CowStr<'_>) -> bool {
+        let style = self.current_style().patch(self.options.theme.inline_code);
         if let Some(table) = self.table.as_mut() {
             if table.is_collecting() {
-                table.push_code(text);
+                table.push_text(text, style);
                 return true;
             }
         }
@@ -659,9 +1072,10 @@ impl MarkdownBuffer {
     }
 
     fn push_table_soft_break(&mut self) -> bool {
+        let style = self.current_style();
         if let Some(table) = self.table.as_mut() {
             if table.is_collecting() {
-                table.push_soft_break();
+                table.push_soft_break(style);
                 return true;
             }
         }
@@ -681,7 +1095,7 @@ impl MarkdownBuffer {
     fn insert_prefixes(&mut self) {
         if self.code_block.is_active() {
             self.lines
-                .push_span(Span::styled(" ", Self::code_block_style()), false);
+                .push_span(Span::styled(" ", self.options.theme.code_block()), false);
         }
         if self.blockquote_depth > 0 {
             for level in 0..self.blockquote_depth {
@@ -690,9 +1104,9 @@ impl MarkdownBuffer {
                 } else {
                     "│ "
                 };
-                let mut style = Style::default().fg(blockquote_color(level));
+                let mut style = Style::default().fg(self.options.theme.blockquote_bar(level));
                 if self.code_block.is_active() {
-                    style = style.bg(CODE_BLOCK_BG);
+                    style = style.bg(self.options.theme.code_bg);
                 }
                 self.lines
                     .push_span(Span::styled(marker.to_string(), style), false);
@@ -701,7 +1115,7 @@ impl MarkdownBuffer {
     }
 
     fn finish_code_block(&mut self) {
-        let Some((start, language)) = self.code_block.take() else {
+        let Some((start, language, source)) = self.code_block.take() else {
             return;
         };
         if start > self.lines.len() {
@@ -716,6 +1130,8 @@ impl MarkdownBuffer {
                 line_start: start,
                 line_end: end,
                 language,
+                source,
+                source_range: self.code_block.source_start..self.lines.source.end,
             });
         }
     }
@@ -752,61 +1168,50 @@ impl MarkdownBuffer {
     }
 
     fn finalize(self) -> RenderedMarkdown {
-        let (lines, headings) = self.lines.finalize();
+        let (lines, line_sources, headings) = self.lines.finalize();
         RenderedMarkdown {
             lines,
+            line_sources,
             headings,
             code_blocks: self.code_blocks,
+            tables: self.tables,
             rules: self.rule_lines,
-        }
-    }
-
-    fn heading_text_style(&self, level: pulldown_cmark::HeadingLevel) -> Style {
-        match level {
-            pulldown_cmark::HeadingLevel::H1 => Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-            pulldown_cmark::HeadingLevel::H2 => Style::default()
-                .fg(Color::LightBlue)
-                .add_modifier(Modifier::BOLD),
-            pulldown_cmark::HeadingLevel::H3 => Style::default()
-                .fg(Color::LightMagenta)
-                .add_modifier(Modifier::BOLD),
-            pulldown_cmark::HeadingLevel::H4 => Style::default().fg(Color::Magenta),
-            pulldown_cmark::HeadingLevel::H5 => Style::default()
-                .fg(Color::Magenta)
-                .add_modifier(Modifier::ITALIC),
-            pulldown_cmark::HeadingLevel::H6 => Style::default()
-                .fg(Color::Gray)
-                .add_modifier(Modifier::ITALIC),
+            links: self.links,
+            images: self.images,
         }
     }
 }
 
-impl MarkdownBuffer {
-    fn code_block_style() -> Style {
-        Style::default().fg(CODE_BLOCK_FG).bg(CODE_BLOCK_BG)
-    }
-}
-
 fn unordered_bullet(depth: usize) -> &'static str {
     const BULLETS: [&str; 4] = ["●", "○", "■", "□"];
     BULLETS[depth % BULLETS.len()]
 }
 
-fn blockquote_color(depth: usize) -> Color {
-    BLOCKQUOTE_COLORS[depth % BLOCKQUOTE_COLORS.len()]
-}
-
 struct TableBuilder {
     alignments: Vec<Alignment>,
     header: Option<Vec<Cell>>,
     rows: Vec<Vec<Cell>>,
     current_row: Vec<Cell>,
-    current_cell: String,
+    current_cell: Vec<Vec<CellSpan>>,
     in_head: bool,
     in_cell: bool,
     max_width: usize,
+    /// Destinations of the links met in cells, indexed by `CellSpan::link`.
+    links: Vec<String>,
+    active_link: Option<usize>,
+}
+
+/// A span of cell text and the link it belongs to, if any.
+#[derive(Clone)]
+struct CellSpan {
+    span: Span<'static>,
+    link: Option<usize>,
+}
+
+impl CellSpan {
+    fn plain(span: Span<'static>) -> Self {
+        Self { span, link: None }
+    }
 }
 
 impl TableBuilder {
@@ -816,13 +1221,24 @@ impl TableBuilder {
             header: None,
             rows: Vec::new(),
             current_row: Vec::new(),
-            current_cell: String::new(),
+            current_cell: Vec::new(),
             in_head: false,
             in_cell: false,
             max_width,
+            links: Vec::new(),
+            active_link: None,
         }
     }
 
+    fn start_link(&mut self, destination: String) {
+        self.active_link = Some(self.links.len());
+        self.links.push(destination);
+    }
+
+    fn end_link(&mut self) {
+        self.active_link = None;
+    }
+
     fn start_head(&mut self) {
         if self.in_cell {
             self.end_cell();
@@ -864,7 +1280,7 @@ impl TableBuilder {
         if self.in_cell {
             self.end_cell();
         }
-        self.current_cell.clear();
+        self.current_cell = vec![Vec::new()];
         self.in_cell = true;
     }
 
@@ -872,8 +1288,8 @@ impl TableBuilder {
         if !self.in_cell {
             return;
         }
-        let raw = mem::take(&mut self.current_cell);
-        let cell = Cell::from_raw(raw);
+        let lines = mem::take(&mut self.current_cell);
+        let cell = Cell::from_lines(lines);
         self.current_row.push(cell);
         self.in_cell = false;
     }
@@ -888,28 +1304,34 @@ impl TableBuilder {
         self.current_row.clear();
     }
 
-    fn push_text(&mut self, text: &CowStr<'_>) {
-        if !self.in_cell {
+    fn push_span(&mut self, span: Span<'static>) {
+        if !self.in_cell || span.content.is_empty() {
             return;
         }
-        self.current_cell.push_str(text.as_ref());
-    }
-
-    fn push_code(&mut self, text: &CowStr<'_>) {
-        if !self.in_cell {
+        let link = self.active_link;
+        let Some(line) = self.current_cell.last_mut() else {
             return;
+        };
+        match line.last_mut() {
+            Some(last) if last.span.style == span.style && last.link == link => {
+                last.span.content.to_mut().push_str(&span.content);
+            }
+            _ => line.push(CellSpan { span, link }),
         }
-        self.current_cell.push('`');
-        self.current_cell.push_str(text.as_ref());
-        self.current_cell.push('`');
     }
 
-    fn push_soft_break(&mut self) {
-        if !self.in_cell {
-            return;
-        }
-        if !self.current_cell.ends_with(' ') {
-            self.current_cell.push(' ');
+    fn push_text(&mut self, text: &CowStr<'_>, style: Style) {
+        self.push_span(Span::styled(text.to_string(), style));
+    }
+
+    fn push_soft_break(&mut self, style: Style) {
+        let ends_with_space = self
+            .current_cell
+            .last()
+            .and_then(|line| line.last())
+            .is_some_and(|last| last.span.content.ends_with(' '));
+        if !ends_with_space {
+            self.push_span(Span::styled(" ", style));
         }
     }
 
@@ -917,17 +1339,14 @@ impl TableBuilder {
         if !self.in_cell {
             return;
         }
-        self.current_cell.push('\n');
+        self.current_cell.push(Vec::new());
     }
 
-    fn push_html(&mut self, html: &CowStr<'_>) {
-        if !self.in_cell {
-            return;
-        }
+    fn push_html(&mut self, html: &CowStr<'_>, style: Style) {
         if is_html_break(html.as_ref()) {
-            self.current_cell.push('\n');
+            self.push_hard_break();
         } else {
-            self.current_cell.push_str(html.as_ref());
+            self.push_text(html, style);
         }
     }
 
@@ -935,7 +1354,9 @@ impl TableBuilder {
         self.in_cell
     }
 
-    fn into_lines(mut self) -> Vec<Line<'static>> {
+    /// Lays out the table; links in cells come back with their spans
+    /// counted from the table's first line.
+    fn into_lines(mut self) -> (Vec<Line<'static>>, Vec<LinkOverlay>) {
         if self.in_cell {
             self.end_cell();
         }
@@ -951,7 +1372,7 @@ impl TableBuilder {
             col_count = col_count.max(row.len());
         }
         if col_count == 0 {
-            return Vec::new();
+            return (Vec::new(), Vec::new());
         }
 
         if self.alignments.len() < col_count {
@@ -968,45 +1389,51 @@ impl TableBuilder {
         clamp_column_widths(&mut widths, self.max_width);
 
         let mut lines = Vec::new();
+        let mut link_spans = vec![Vec::new(); self.links.len()];
         lines.push(Line::from(unicode_border('┌', '┬', '┐', &widths)));
         if let Some(header) = &self.header {
-            for row_line in build_row_lines(header, &widths, &self.alignments) {
-                lines.push(Line::from(row_line));
-            }
+            build_row_lines(
+                header,
+                &widths,
+                &self.alignments,
+                &mut lines,
+                &mut link_spans,
+            );
             lines.push(Line::from(unicode_border('├', '┼', '┤', &widths)));
         }
         for (idx, row) in self.rows.iter().enumerate() {
-            for row_line in build_row_lines(row, &widths, &self.alignments) {
-                lines.push(Line::from(row_line));
-            }
+            build_row_lines(row, &widths, &self.alignments, &mut lines, &mut link_spans);
             if idx + 1 < self.rows.len() {
                 lines.push(Line::from(unicode_border('├', '┼', '┤', &widths)));
             }
         }
         lines.push(Line::from(unicode_border('└', '┴', '┘', &widths)));
-        lines
+        let links = self
+            .links
+            .into_iter()
+            .zip(link_spans)
+            .filter(|(_, spans)| !spans.is_empty())
+            .map(|(destination, spans)| LinkOverlay { destination, spans })
+            .collect();
+        (lines, links)
     }
 }
 
 #[derive(Clone)]
 struct Cell {
-    lines: Vec<String>,
+    lines: Vec<Vec<CellSpan>>,
 }
 
 impl Cell {
-    fn from_raw(raw: String) -> Self {
-        let trimmed = raw.trim();
-        if trimmed.is_empty() {
-            return Self {
-                lines: vec![String::new()],
-            };
+    fn from_lines(lines: Vec<Vec<CellSpan>>) -> Self {
+        let mut lines: Vec<Vec<CellSpan>> = lines.into_iter().map(trim_spans).collect();
+        while lines.last().is_some_and(Vec::is_empty) {
+            lines.pop();
         }
-        let mut lines: Vec<String> = trimmed
-            .split('\n')
-            .map(|segment| segment.trim().to_string())
-            .collect();
+        let leading = lines.iter().take_while(|line| line.is_empty()).count();
+        lines.drain(..leading);
         if lines.is_empty() {
-            lines.push(String::new());
+            lines.push(Vec::new());
         }
         Self { lines }
     }
@@ -1014,12 +1441,39 @@ impl Cell {
     fn width(&self) -> usize {
         self.lines
             .iter()
-            .map(|line| display_width(line))
+            .map(|line| spans_width(line).max(1))
             .max()
             .unwrap_or(0)
     }
 }
 
+fn spans_width(spans: &[CellSpan]) -> usize {
+    spans.iter().map(|piece| piece.span.width()).sum()
+}
+
+/// Drops leading and trailing whitespace across span boundaries.
+fn trim_spans(mut spans: Vec<CellSpan>) -> Vec<CellSpan> {
+    while let Some(first) = spans.first_mut() {
+        let trimmed = first.span.content.trim_start();
+        if trimmed.is_empty() {
+            spans.remove(0);
+        } else {
+            first.span.content = trimmed.to_string().into();
+            break;
+        }
+    }
+    while let Some(last) = spans.last_mut() {
+        let trimmed = last.span.content.trim_end();
+        if trimmed.is_empty() {
+            spans.pop();
+        } else {
+            last.span.content = trimmed.to_string().into();
+            break;
+        }
+    }
+    spans
+}
+
 fn update_widths(widths: &mut [usize], row: &[Cell]) {
     for (idx, cell) in row.iter().enumerate() {
         if idx < widths.len() {
@@ -1028,16 +1482,19 @@ fn update_widths(widths: &mut [usize], row: &[Cell]) {
     }
 }
 
-fn display_width(value: &str) -> usize {
-    let width = UnicodeWidthStr::width(value);
-    width.max(1)
-}
-
-fn build_row_lines(row: &[Cell], widths: &[usize], alignments: &[Alignment]) -> Vec<String> {
+/// Appends the lines of one table row to `lines`, recording where each
+/// link's text lands in `link_spans`.
+fn build_row_lines(
+    row: &[Cell],
+    widths: &[usize],
+    alignments: &[Alignment],
+    lines: &mut Vec<Line<'static>>,
+    link_spans: &mut [Vec<LinkSpan>],
+) {
     if widths.is_empty() {
-        return Vec::new();
+        return;
     }
-    let mut column_lines: Vec<Vec<String>> = widths
+    let mut column_lines: Vec<Vec<Vec<CellSpan>>> = widths
         .iter()
         .enumerate()
         .map(|(idx, width)| render_cell_lines(row.get(idx), *width, alignments[idx]))
@@ -1049,43 +1506,53 @@ fn build_row_lines(row: &[Cell], widths: &[usize], alignments: &[Alignment]) ->
         .unwrap_or(1);
     for (col_idx, lines) in column_lines.iter_mut().enumerate() {
         while lines.len() < height {
-            lines.push(pad_cell("", widths[col_idx], alignments[col_idx]));
+            lines.push(pad_cell(Vec::new(), widths[col_idx], alignments[col_idx]));
         }
     }
-    let mut rows = Vec::with_capacity(height);
     for line_idx in 0..height {
-        let mut line = String::new();
-        line.push('│');
-        for col_idx in 0..widths.len() {
-            line.push(' ');
-            line.push_str(&column_lines[col_idx][line_idx]);
-            line.push(' ');
-            line.push('│');
+        let line = lines.len();
+        let mut spans = vec![Span::raw("│")];
+        let mut end = "│".len();
+        for cell_lines in &mut column_lines {
+            spans.push(Span::raw(" "));
+            end += 1;
+            for piece in cell_lines[line_idx].drain(..) {
+                let start = end;
+                end += piece.span.content.len();
+                if let Some(link) = piece.link {
+                    match link_spans[link].last_mut() {
+                        Some(last) if last.line == line && last.end == start => last.end = end,
+                        _ => link_spans[link].push(LinkSpan { line, start, end }),
+                    }
+                }
+                spans.push(piece.span);
+            }
+            spans.push(Span::raw(" │"));
+            end += " │".len();
         }
-        rows.push(line);
+        lines.push(Line::from(spans));
     }
-    rows
 }
 
-fn pad_cell(text: &str, width: usize, alignment: Alignment) -> String {
-    let trimmed = text.trim();
-    if trimmed.is_empty() {
-        return " ".repeat(width);
-    }
-    let display = UnicodeWidthStr::width(trimmed);
+fn pad_cell(spans: Vec<CellSpan>, width: usize, alignment: Alignment) -> Vec<CellSpan> {
+    let mut spans = trim_spans(spans);
+    let display = spans_width(&spans);
     if display >= width {
-        return trimmed.to_string();
+        return spans;
     }
     let padding = width - display;
-    match alignment {
-        Alignment::Right => format!("{}{}", " ".repeat(padding), trimmed),
-        Alignment::Center => {
-            let left = padding / 2;
-            let right = padding - left;
-            format!("{}{}{}", " ".repeat(left), trimmed, " ".repeat(right))
-        }
-        _ => format!("{}{}", trimmed, " ".repeat(padding)),
-    }
+    let (left, right) = match alignment {
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+        _ => (0, padding),
+    };
+    if left > 0 {
+        spans.insert(0, CellSpan::plain(Span::raw(" ".repeat(left))));
+    }
+    if right > 0 {
+        spans.push(CellSpan::plain(Span::raw(" ".repeat(right))));
+    }
+    spans
 }
 
 fn unicode_border(left: char, junction: char, right: char, widths: &[usize]) -> String {
@@ -1164,38 +1631,75 @@ fn adjust_widths(widths: &mut [usize], target: usize) {
     }
 }
 
-fn render_cell_lines(cell: Option<&Cell>, width: usize, alignment: Alignment) -> Vec<String> {
+fn render_cell_lines(
+    cell: Option<&Cell>,
+    width: usize,
+    alignment: Alignment,
+) -> Vec<Vec<CellSpan>> {
     let mut rendered = Vec::new();
     if let Some(cell) = cell {
-        for raw_line in &cell.lines {
-            let wrapped = wrap_cell_text(raw_line, width);
+        for line in &cell.lines {
+            let wrapped = wrap_cell_text(line, width);
             if wrapped.is_empty() {
-                rendered.push(pad_cell("", width, alignment));
+                rendered.push(pad_cell(Vec::new(), width, alignment));
             } else {
                 for segment in wrapped {
-                    rendered.push(pad_cell(&segment, width, alignment));
+                    rendered.push(pad_cell(segment, width, alignment));
                 }
             }
         }
     }
     if rendered.is_empty() {
-        rendered.push(pad_cell("", width, alignment));
+        rendered.push(pad_cell(Vec::new(), width, alignment));
     }
     rendered
 }
 
-fn wrap_cell_text(text: &str, width: usize) -> Vec<String> {
+/// Wraps the plain text with `textwrap`, then walks the original spans to
+/// give every character of each wrapped segment its style back.
+fn wrap_cell_text(spans: &[CellSpan], width: usize) -> Vec<Vec<CellSpan>> {
     if width == 0 {
-        return vec![String::new()];
+        return vec![Vec::new()];
     }
+    let text: String = spans
+        .iter()
+        .map(|piece| piece.span.content.as_ref())
+        .collect();
     let trimmed = text.trim();
     if trimmed.is_empty() {
-        return vec![String::new()];
-    }
+        return vec![Vec::new()];
+    }
+    let mut styled = spans.iter().flat_map(|piece| {
+        piece
+            .span
+            .content
+            .chars()
+            .map(move |ch| (ch, piece.span.style, piece.link))
+    });
     wrap(trimmed, WrapOptions::new(width).break_words(true))
         .into_iter()
-        .map(|segment| segment.into_owned())
-        .collect::<Vec<_>>()
+        .map(|segment| {
+            let mut line: Vec<CellSpan> = Vec::new();
+            for ch in segment.chars() {
+                // Skips the whitespace textwrap dropped at the break.
+                let Some((_, style, link)) =
+                    styled.by_ref().find(|(original, _, _)| *original == ch)
+                else {
+                    break;
+                };
+                match line.last_mut() {
+                    Some(last) if last.span.style == style && last.link == link => {
+                        last.span.content.to_mut().push(ch)
+                    }
+                    _ => line.push(CellSpan {
+                        span: Span::styled(ch.to_string(), style),
+                        link,
+                    }),
+                }
+            }
+            line
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -1335,6 +1839,46 @@ mod tests {
         assert_eq!(line_row_span(&line, 20), 1);
         assert_eq!(line_row_span(&line, 5), 2);
         assert_eq!(line_row_span(&line, 3), 4);
+        assert_eq!(line_row_span(&Line::from("aaa bbb ccc"), 5), 3);
+    }
+
+    #[test]
+    fn wrap_rows_match_the_paragraph_layout() {
+        use ratatui::{
+            buffer::Buffer,
+            layout::Rect,
+            widgets::{Paragraph, Widget, Wrap},
+        };
+        let texts = [
+            "the quick brown fox jumps over the lazy dog",
+            "  leading and   several   spaces  ",
+            "averyveryverylongwordthatmustbreak and more",
+            "wide 漢字の文章 mixed with text 漢字漢字",
+            "ends exactly here",
+            "trailing spaces at the break      next",
+            "\u{a0}no\u{a0}break\u{a0}spaces and a zero\u{200b}width space",
+        ];
+        for text in texts {
+            for width in [1, 2, 5, 7, 10, 17, 40] {
+                let line = Line::from(vec![Span::raw(text)]);
+                let area = Rect::new(0, 0, width as u16, 100);
+                let mut buf = Buffer::empty(area);
+                Paragraph::new(line.clone())
+                    .wrap(Wrap { trim: false })
+                    .render(area, &mut buf);
+                let rows = wrap_rows(&line, width);
+                let mut expected = Buffer::empty(area);
+                for (y, row) in rows.iter().enumerate() {
+                    let mut x = 0;
+                    for &(at, ch_width) in row {
+                        let ch = text[at..].chars().next().unwrap();
+                        expected.get_mut(x as u16, y as u16).set_char(ch);
+                        x += ch_width;
+                    }
+                }
+                assert_eq!(buf, expected, "{text:?} at width {width}");
+            }
+        }
     }
 
     #[test]
@@ -1424,8 +1968,8 @@ mod tests {
     }
 
     #[test]
-    fn code_block_overlay_records_language_and_lines() {
-        let markdown = "before\n```rust\nfn main() {}\n```\nafter\n";
+    fn code_block_overlay_records_language_lines_and_source() {
+        let markdown = "before\n```rust\nfn main() {}\n\n  let x = 1;\n```\nafter\n";
         let render = markdown_to_render(markdown);
         assert_eq!(render.code_blocks.len(), 1);
         let block = &render.code_blocks[0];
@@ -1437,6 +1981,11 @@ mod tests {
             .flat_map(|line| line.spans.iter().map(|s| s.content.as_ref()))
             .collect();
         assert!(combined.contains("fn main() {}"));
+        assert_eq!(block.source, "fn main() {}\n\n  let x = 1;\n");
+        assert_eq!(
+            &markdown[block.source_range.clone()],
+            "```rust\nfn main() {}\n\n  let x = 1;\n```"
+        );
     }
 
     #[test]
@@ -1454,10 +2003,33 @@ mod tests {
             .expect("code line rendered");
         assert!(!code_line.spans.is_empty());
         for span in &code_line.spans {
-            assert_eq!(span.style.bg, Some(CODE_BLOCK_BG));
+            assert_eq!(span.style.bg, Some(Theme::default().code_bg));
         }
     }
 
+    #[test]
+    fn fenced_code_is_syntax_highlighted() {
+        let markdown = "```rust\nfn main() {}\n```\n\n```\nfn main() {}\n```\n";
+        let render = markdown_to_render(markdown);
+        let fn_spans: Vec<&Span<'static>> = render
+            .lines
+            .iter()
+            .flat_map(|line| line.spans.iter())
+            .filter(|span| span.content.as_ref() == "fn")
+            .collect();
+        assert_eq!(fn_spans.len(), 1);
+        assert_ne!(fn_spans[0].style.fg, Some(Theme::default().code_fg));
+        assert_eq!(fn_spans[0].style.bg, Some(Theme::default().code_bg));
+        assert!(render
+            .lines
+            .iter()
+            .any(|line| line
+                .spans
+                .iter()
+                .any(|span| span.content.as_ref() == "fn main() {}"
+                    && span.style.fg == Some(Theme::default().code_fg))));
+    }
+
     #[test]
     fn code_block_background_stops_after_block() {
         let markdown = "```bash\ncmd\n```\nnext";
@@ -1468,22 +2040,192 @@ mod tests {
         assert!(after
             .spans
             .iter()
-            .all(|span| span.style.bg != Some(CODE_BLOCK_BG)));
+            .all(|span| span.style.bg != Some(Theme::default().code_bg)));
     }
 
     #[test]
-    fn heading_block_colors_are_distinct() {
-        let colors: Vec<_> = [
-            pulldown_cmark::HeadingLevel::H1,
-            pulldown_cmark::HeadingLevel::H2,
-            pulldown_cmark::HeadingLevel::H3,
-            pulldown_cmark::HeadingLevel::H4,
-            pulldown_cmark::HeadingLevel::H5,
-            pulldown_cmark::HeadingLevel::H6,
-        ]
-        .iter()
-        .map(|lvl| heading_block_colors(*lvl))
-        .collect();
-        assert!(colors.windows(2).all(|pair| pair[0] != pair[1]));
+    fn line_sources_point_back_to_markdown() {
+        let markdown = "# Title\n\nfirst line\nsecond line\n\n```\nlet a = 1;\nlet b = 2;\n```\n";
+        let render = markdown_to_render(markdown);
+        assert_eq!(render.line_sources.len(), render.lines.len());
+        for (line, range) in render.lines.iter().zip(&render.line_sources) {
+            let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+            let text = text.trim();
+            if !text.is_empty() {
+                assert!(
+                    markdown[range.clone()].contains(text),
+                    "{text:?} not found in {:?}",
+                    &markdown[range.clone()]
+                );
+            }
+        }
+        assert!(render
+            .line_sources
+            .windows(2)
+            .all(|pair| pair[0].start <= pair[1].start));
+    }
+
+    #[test]
+    fn links_record_destination_and_position() {
+        let markdown = "> see [the docs](docs/guide.md) and\n> [more\ntext](https://example.com)\n";
+        let render = markdown_to_render(markdown);
+        assert_eq!(render.links.len(), 2);
+        let text_of = |line: usize| -> String {
+            render.lines[line]
+                .spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect()
+        };
+        let first = &render.links[0];
+        assert_eq!(first.destination, "docs/guide.md");
+        assert_eq!(first.spans.len(), 1);
+        let span = first.spans[0];
+        assert_eq!(&text_of(span.line)[span.start..span.end], "the docs");
+        let second = &render.links[1];
+        assert_eq!(second.destination, "https://example.com");
+        let pieces: Vec<String> = second
+            .spans
+            .iter()
+            .map(|span| text_of(span.line)[span.start..span.end].to_string())
+            .collect();
+        assert_eq!(pieces, vec!["more", "text"]);
+    }
+
+    #[test]
+    fn links_in_table_cells_are_recorded() {
+        let markdown = "intro [first](a.md)\n\n| Name | See |\n|---|---|\n| x | [see the guide](guide.md) and [ÿ](b.md) |\n\n[after](c.md)\n";
+        let options = MarkdownOptions {
+            max_table_width: 20,
+            ..MarkdownOptions::default()
+        };
+        let render = markdown_to_render_with_options(markdown, options);
+        let text_of = |span: &LinkSpan| -> String {
+            let line: String = render.lines[span.line]
+                .spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect();
+            line[span.start..span.end].to_string()
+        };
+        let destinations: Vec<&str> = render
+            .links
+            .iter()
+            .map(|link| link.destination.as_str())
+            .collect();
+        assert_eq!(destinations, ["a.md", "guide.md", "b.md", "c.md"]);
+        let guide: Vec<String> = render.links[1].spans.iter().map(text_of).collect();
+        assert_eq!(guide, ["see the", "guide"]);
+        assert_eq!(text_of(&render.links[2].spans[0]), "ÿ");
+        assert_eq!(text_of(&render.links[3].spans[0]), "after");
+    }
+
+    #[test]
+    fn images_render_alt_title_and_path() {
+        let markdown =
+            "Intro ![a *chart*](plot.png \"Q3\") more\n\n| Pic |\n| --- |\n| ![logo](l.svg) |";
+        let render = markdown_to_render(markdown);
+        let joined: Vec<String> = render
+            .lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>()
+            })
+            .collect();
+        assert_eq!(joined[0], "Intro ");
+        assert_eq!(joined[1], "🖼 a chart — Q3 (plot.png)");
+        assert_eq!(joined[2], "more");
+        assert!(joined
+            .iter()
+            .any(|line| line.contains("│ 🖼 logo (l.svg) │")));
+    }
+
+    #[test]
+    fn images_in_quotes_and_lists_keep_their_prefixes() {
+        let dir = std::env::temp_dir().join(format!("md-viewer-images-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(4, 4)
+            .save(dir.join("pic.png"))
+            .unwrap();
+        let options = MarkdownOptions {
+            image_dir: Some(dir.clone()),
+            ..MarkdownOptions::default()
+        };
+        let render =
+            markdown_to_render_with_options("> ![pic](pic.png)\n\n- ![pic](pic.png)\n", options);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(render.images.len(), 2);
+        let text = |idx: usize| {
+            render.lines[idx]
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>()
+        };
+        let quoted = &render.images[0];
+        assert_eq!(quoted.offset, 2);
+        assert_eq!(text(quoted.line), "▌ ");
+        assert_eq!(text(quoted.line + quoted.rows), "▌ 🖼 pic (pic.png)");
+        let listed = &render.images[1];
+        assert_eq!(listed.offset, 2);
+        assert_eq!(text(listed.line + listed.rows), "  🖼 pic (pic.png)");
+    }
+
+    #[test]
+    fn table_cells_keep_inline_styles_across_wraps() {
+        let markdown = "| A | B |\n| --- | --- |\n| **bold words here** | `x` |";
+        let options = MarkdownOptions {
+            max_table_width: 20,
+            ..MarkdownOptions::default()
+        };
+        let render = markdown_to_render_with_options(markdown, options);
+        let bold: Vec<&str> = render
+            .lines
+            .iter()
+            .flat_map(|line| &line.spans)
+            .filter(|span| span.style.add_modifier.contains(Modifier::BOLD))
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert!(bold.len() > 1, "{bold:?}");
+        assert_eq!(bold.concat().replace(' ', ""), "boldwordshere");
+        let code = render
+            .lines
+            .iter()
+            .flat_map(|line| &line.spans)
+            .find(|span| span.content == "x")
+            .expect("code span present");
+        assert_eq!(code.style, Theme::default().inline_code);
+        assert!(!render
+            .lines
+            .iter()
+            .any(|line| line.to_string().contains('`')));
+    }
+
+    #[test]
+    fn slice_line_cuts_columns_and_wide_characters() {
+        let line = Line::from(vec![Span::raw("ab"), Span::raw("界cd")]);
+        assert_eq!(slice_line(&line, 1, 3).to_string(), "b界");
+        assert_eq!(slice_line(&line, 1, 2).to_string(), "b ");
+        assert_eq!(slice_line(&line, 3, 3).to_string(), " cd");
+        assert_eq!(slice_line(&line, 10, 3).to_string(), "");
+    }
+
+    #[test]
+    fn no_wrap_tables_keep_their_natural_width() {
+        let markdown = "| A | B |\n| --- | --- |\n| a rather long cell | x |\n\n| C |\n| --- |\n| another long cell |";
+        let options = MarkdownOptions {
+            max_table_width: 16,
+            no_wrap_tables: HashSet::from([1]),
+            ..MarkdownOptions::default()
+        };
+        let render = markdown_to_render_with_options(markdown, options);
+        assert_eq!(render.tables.len(), 2);
+        let [first, second] = [render.tables[0], render.tables[1]];
+        assert!(first.line_end - first.line_start > 5);
+        assert_eq!(second.line_end - second.line_start, 5);
+        assert_eq!(render.lines[second.line_start].width(), 21);
     }
 }
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::anchor::ScrollAnchor;
+use crate::history::HistoryEntry;
+
+/// How many positions the jump list remembers, like vim's default of 100.
+const JUMP_LIST_LIMIT: usize = 100;
+
+/// Lowercase marks belong to one document; uppercase marks are global and
+/// remember which document they were set in.
+#[derive(Default)]
+pub struct Marks {
+    local: HashMap<(PathBuf, char), ScrollAnchor>,
+    global: HashMap<char, HistoryEntry>,
+}
+
+impl Marks {
+    pub fn set(&mut self, name: char, path: &Path, anchor: ScrollAnchor) {
+        if name.is_ascii_uppercase() {
+            let entry = HistoryEntry {
+                path: path.to_path_buf(),
+                anchor: Some(anchor),
+            };
+            self.global.insert(name, entry);
+        } else {
+            self.local.insert((path.to_path_buf(), name), anchor);
+        }
+    }
+
+    pub fn get(&self, name: char, path: &Path) -> Option<HistoryEntry> {
+        if name.is_ascii_uppercase() {
+            return self.global.get(&name).cloned();
+        }
+        let anchor = self.local.get(&(path.to_path_buf(), name))?;
+        Some(HistoryEntry {
+            path: path.to_path_buf(),
+            anchor: Some(anchor.clone()),
+        })
+    }
+}
+
+/// Positions left by big jumps within the current document, walked with
+/// `Ctrl-o` / `Ctrl-i`.
+#[derive(Default)]
+pub struct JumpList {
+    entries: Vec<ScrollAnchor>,
+    index: usize,
+}
+
+impl JumpList {
+    /// Records `here` before a jump, dropping anything ahead of the current
+    /// position in the list.
+    pub fn record(&mut self, here: ScrollAnchor) {
+        self.entries.truncate(self.index);
+        if self.entries.last() != Some(&here) {
+            self.entries.push(here);
+        }
+        if self.entries.len() > JUMP_LIST_LIMIT {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    pub fn back(&mut self, here: ScrollAnchor) -> Option<ScrollAnchor> {
+        if self.index == 0 {
+            return None;
+        }
+        if self.index == self.entries.len() {
+            if self.entries.last() == Some(&here) {
+                // Already standing on the newest entry, so step past it.
+                self.index -= 1;
+                if self.index == 0 {
+                    return None;
+                }
+            } else {
+                // Remember where we left from so `forward` can return here.
+                self.entries.push(here);
+            }
+        }
+        self.index -= 1;
+        self.entries.get(self.index).cloned()
+    }
+
+    pub fn forward(&mut self) -> Option<ScrollAnchor> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        self.entries.get(self.index).cloned()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(source_line: usize) -> ScrollAnchor {
+        ScrollAnchor {
+            heading: None,
+            source_line,
+            line_delta: 0,
+            row_offset: 0,
+        }
+    }
+
+    #[test]
+    fn jump_list_walks_back_and_forward() {
+        let mut jumps = JumpList::default();
+        jumps.record(at(1));
+        jumps.record(at(20));
+        assert_eq!(jumps.back(at(40)), Some(at(20)));
+        assert_eq!(jumps.back(at(20)), Some(at(1)));
+        assert_eq!(jumps.back(at(1)), None);
+        assert_eq!(jumps.forward(), Some(at(20)));
+        assert_eq!(jumps.forward(), Some(at(40)));
+        assert_eq!(jumps.forward(), None);
+
+        jumps.back(at(40));
+        jumps.record(at(20));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(at(60)), Some(at(20)));
+    }
+
+    #[test]
+    fn back_from_the_newest_entry_skips_it() {
+        let mut jumps = JumpList::default();
+        jumps.record(at(1));
+        jumps.record(at(20));
+        assert_eq!(jumps.back(at(20)), Some(at(1)));
+        assert_eq!(jumps.forward(), Some(at(20)));
+        assert_eq!(jumps.forward(), None);
+
+        let mut jumps = JumpList::default();
+        jumps.record(at(5));
+        assert_eq!(jumps.back(at(5)), None);
+    }
+
+    #[test]
+    fn lowercase_marks_are_per_document() {
+        let mut marks = Marks::default();
+        marks.set('a', Path::new("one.md"), at(5));
+        marks.set('A', Path::new("one.md"), at(7));
+        assert!(marks.get('a', Path::new("two.md")).is_none());
+        let global = marks.get('A', Path::new("two.md")).unwrap();
+        assert_eq!(global.path, Path::new("one.md"));
+        assert_eq!(global.anchor, Some(at(7)));
+    }
+}
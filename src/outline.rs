@@ -0,0 +1,154 @@
+use pulldown_cmark::HeadingLevel;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+pub struct OutlineEntry {
+    pub level: HeadingLevel,
+    pub title: String,
+}
+
+#[derive(Default)]
+pub struct OutlineState {
+    open: bool,
+    focused: bool,
+    selected: usize,
+    offset: usize,
+}
+
+impl OutlineState {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.open && self.focused
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Index of the first entry visible in the pane as of the last draw.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn open(&mut self, selected: usize) {
+        self.open = true;
+        self.focused = true;
+        self.selected = selected;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.focused = false;
+    }
+
+    pub fn focus(&mut self, selected: usize) {
+        self.focused = true;
+        self.selected = selected;
+    }
+
+    pub fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    pub fn select(&mut self, idx: usize, len: usize) {
+        self.selected = idx.min(len.saturating_sub(1));
+    }
+
+    pub fn move_selection(&mut self, delta: isize, len: usize) {
+        if len == 0 {
+            self.selected = 0;
+            return;
+        }
+        let next = self.selected as isize + delta;
+        self.selected = next.clamp(0, len as isize - 1) as usize;
+    }
+}
+
+pub fn width_for(total_width: u16) -> u16 {
+    (total_width / 3)
+        .clamp(16, 36)
+        .min(total_width.saturating_sub(10))
+}
+
+pub fn render_outline(
+    frame: &mut Frame<'_>,
+    area: Rect,
+    entries: &[OutlineEntry],
+    current: Option<usize>,
+    state: &mut OutlineState,
+    theme: &Theme,
+) {
+    let border_style = if state.is_focused() {
+        Style::default().fg(theme.ui.accent)
+    } else {
+        Style::default().fg(theme.ui.muted)
+    };
+    let block = Block::default()
+        .title("Outline")
+        .borders(Borders::ALL)
+        .border_style(border_style);
+    if entries.is_empty() {
+        let empty = List::new([ListItem::new(Line::from(Span::styled(
+            "(no headings)",
+            Style::default().fg(theme.ui.muted),
+        )))])
+        .block(block);
+        frame.render_widget(empty, area);
+        state.offset = 0;
+        return;
+    }
+    let items: Vec<ListItem<'static>> = entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let depth = heading_depth(entry.level);
+            let marker = if Some(idx) == current { "▸ " } else { "  " };
+            let mut style = Style::default().fg(theme.outline_level(entry.level));
+            if depth == 0 {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if Some(idx) == current {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            ListItem::new(Line::from(vec![
+                Span::styled(marker, Style::default().fg(theme.ui.highlight)),
+                Span::raw("  ".repeat(depth)),
+                Span::styled(entry.title.clone(), style),
+            ]))
+        })
+        .collect();
+    let highlight = if state.is_focused() {
+        Style::default().add_modifier(Modifier::REVERSED)
+    } else {
+        Style::default()
+    };
+    let list = List::new(items).block(block).highlight_style(highlight);
+    let mut list_state = ListState::default().with_selected(Some(if state.is_focused() {
+        state.selected()
+    } else {
+        current.unwrap_or(0)
+    }));
+    frame.render_stateful_widget(list, area, &mut list_state);
+    state.offset = list_state.offset();
+}
+
+fn heading_depth(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 0,
+        HeadingLevel::H2 => 1,
+        HeadingLevel::H3 => 2,
+        HeadingLevel::H4 => 3,
+        HeadingLevel::H5 => 4,
+        HeadingLevel::H6 => 5,
+    }
+}
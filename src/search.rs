@@ -0,0 +1,253 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Default)]
+pub struct SearchState {
+    query: String,
+    input: Option<String>,
+    matches: Vec<SearchMatch>,
+    current: Option<usize>,
+    origin_scroll: usize,
+}
+
+impl SearchState {
+    pub fn is_editing(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn input(&self) -> Option<&str> {
+        self.input.as_deref()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn matches(&self) -> &[SearchMatch] {
+        &self.matches
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn origin_scroll(&self) -> usize {
+        self.origin_scroll
+    }
+
+    pub fn begin_input(&mut self, scroll: usize) {
+        self.input = Some(String::new());
+        self.origin_scroll = scroll;
+    }
+
+    pub fn input_mut(&mut self) -> Option<&mut String> {
+        self.input.as_mut()
+    }
+
+    pub fn finish_input(&mut self) {
+        self.input = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.input = None;
+        self.matches.clear();
+        self.current = None;
+    }
+
+    pub fn update(&mut self, query: &str, lines: &[Line<'_>]) {
+        self.query = query.to_string();
+        self.matches = find_matches(lines, query);
+        if self.matches.is_empty() {
+            self.current = None;
+        } else if let Some(idx) = self.current {
+            self.current = Some(idx.min(self.matches.len() - 1));
+        }
+    }
+
+    pub fn refresh(&mut self, lines: &[Line<'_>]) {
+        let query = self.query.clone();
+        self.update(&query, lines);
+    }
+
+    pub fn select(&mut self, idx: usize) -> Option<SearchMatch> {
+        let found = self.matches.get(idx).copied()?;
+        self.current = Some(idx);
+        Some(found)
+    }
+
+    pub fn first_from_line(&self, line: usize) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        Some(
+            self.matches
+                .iter()
+                .position(|m| m.line >= line)
+                .unwrap_or(0),
+        )
+    }
+}
+
+/// Finds every occurrence of `query` in the rendered lines. Matching is
+/// smart-case: an all-lowercase query ignores case.
+pub fn find_matches(lines: &[Line<'_>], query: &str) -> Vec<SearchMatch> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    let needle: Vec<char> = query.chars().collect();
+    let mut matches = Vec::new();
+    for (line_idx, line) in lines.iter().enumerate() {
+        let text = line_text(line);
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut idx = 0;
+        while idx + needle.len() <= chars.len() {
+            let hit = needle
+                .iter()
+                .zip(&chars[idx..idx + needle.len()])
+                .all(|(&q, &(_, c))| chars_equal(q, c, ignore_case));
+            if hit {
+                let start = chars[idx].0;
+                let end = chars
+                    .get(idx + needle.len())
+                    .map(|(pos, _)| *pos)
+                    .unwrap_or(text.len());
+                matches.push(SearchMatch {
+                    line: line_idx,
+                    start,
+                    end,
+                });
+                idx += needle.len();
+            } else {
+                idx += 1;
+            }
+        }
+    }
+    matches
+}
+
+fn chars_equal(a: char, b: char, ignore_case: bool) -> bool {
+    if a == b {
+        return true;
+    }
+    ignore_case && a.to_lowercase().eq(b.to_lowercase())
+}
+
+pub fn line_text(line: &Line<'_>) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+pub fn match_column(line: &Line<'_>, found: &SearchMatch) -> usize {
+    let text = line_text(line);
+    text.get(..found.start)
+        .map(UnicodeWidthStr::width)
+        .unwrap_or(0)
+}
+
+/// Returns a copy of `line` with the byte ranges in `ranges` restyled.
+/// Ranges must be sorted and non-overlapping.
+pub fn highlight_line(line: &Line<'static>, ranges: &[(usize, usize, Style)]) -> Line<'static> {
+    if ranges.is_empty() {
+        return line.clone();
+    }
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut offset = 0usize;
+    let mut range_iter = ranges.iter().peekable();
+    for span in &line.spans {
+        let text = span.content.as_ref();
+        let span_start = offset;
+        let span_end = offset + text.len();
+        let mut cursor = span_start;
+        while cursor < span_end {
+            let Some(&&(start, end, style)) = range_iter.peek() else {
+                break;
+            };
+            if end <= cursor {
+                range_iter.next();
+                continue;
+            }
+            if start >= span_end {
+                break;
+            }
+            if start > cursor {
+                spans.push(Span::styled(
+                    text[cursor - span_start..start - span_start].to_string(),
+                    span.style,
+                ));
+                cursor = start;
+            }
+            let piece_end = end.min(span_end);
+            spans.push(Span::styled(
+                text[cursor - span_start..piece_end - span_start].to_string(),
+                span.style.patch(style),
+            ));
+            cursor = piece_end;
+            if end <= span_end {
+                range_iter.next();
+            }
+        }
+        if cursor < span_end {
+            spans.push(Span::styled(
+                text[cursor - span_start..].to_string(),
+                span.style,
+            ));
+        }
+        offset = span_end;
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    #[test]
+    fn matches_are_smart_case() {
+        let lines = vec![Line::from("Foo foo FOO"), Line::from("bar")];
+        assert_eq!(find_matches(&lines, "foo").len(), 3);
+        let exact = find_matches(&lines, "Foo");
+        assert_eq!(exact.len(), 1);
+        assert_eq!(
+            exact[0],
+            SearchMatch {
+                line: 0,
+                start: 0,
+                end: 3
+            }
+        );
+    }
+
+    #[test]
+    fn matches_span_multiple_spans() {
+        let lines = vec![Line::from(vec![Span::raw("│ ab"), Span::raw("cd │")])];
+        let found = find_matches(&lines, "bc");
+        assert_eq!(found.len(), 1);
+        let highlighted = highlight_line(
+            &lines[0],
+            &[(found[0].start, found[0].end, Style::new().bg(Color::Yellow))],
+        );
+        let styled: Vec<&str> = highlighted
+            .spans
+            .iter()
+            .filter(|span| span.style.bg == Some(Color::Yellow))
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(styled, vec!["b", "c"]);
+        assert_eq!(line_text(&highlighted), "│ abcd │");
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::HashSet,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::anchor::{HeadingAnchor, ScrollAnchor};
+
+/// Older entries are dropped once the state file remembers this many files.
+const POSITION_LIMIT: usize = 500;
+
+/// Last reading position of each file, kept in `positions.tsv` under the XDG
+/// state directory. Entries are ordered from least to most recently read.
+#[derive(Default)]
+pub struct Positions {
+    file: Option<PathBuf>,
+    entries: Vec<(PathBuf, ScrollAnchor)>,
+    restore: bool,
+    /// Paths read in this session; only these are written back.
+    recorded: HashSet<PathBuf>,
+}
+
+/// `$XDG_STATE_HOME/md-viewer/positions.tsv`, falling back to `~/.local/state`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(base.join("md-viewer").join("positions.tsv"))
+}
+
+impl Positions {
+    /// Reads the state file; with `restore` off positions are still recorded
+    /// but `get` never returns one. A missing or unreadable file starts empty.
+    pub fn load(restore: bool) -> Self {
+        let file = default_path();
+        let entries = file
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| parse(&text))
+            .unwrap_or_default();
+        Self {
+            file,
+            entries,
+            restore,
+            recorded: HashSet::new(),
+        }
+    }
+
+    /// Positions that are restored but never written anywhere.
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            restore: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&ScrollAnchor> {
+        if !self.restore {
+            return None;
+        }
+        let key = key_for(path);
+        self.entries
+            .iter()
+            .rev()
+            .find(|(entry, _)| *entry == key)
+            .map(|(_, anchor)| anchor)
+    }
+
+    pub fn record(&mut self, path: &Path, anchor: ScrollAnchor) {
+        let key = key_for(path);
+        self.recorded.insert(key.clone());
+        upsert(&mut self.entries, key, anchor);
+    }
+
+    /// Merges this session's positions into the file as it is now, so
+    /// viewers open side by side don't drop each other's entries. Writes to
+    /// a temporary file first so an interrupted save never leaves a
+    /// truncated state file behind.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut entries = match fs::read_to_string(file) {
+            Ok(text) => parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        for (path, anchor) in &self.entries {
+            if self.recorded.contains(path) {
+                upsert(&mut entries, path.clone(), anchor.clone());
+            }
+        }
+        // Unique per process so two viewers saving at once don't share it.
+        let temp = file.with_extension(format!("tsv.{}.tmp", std::process::id()));
+        fs::write(&temp, format(&entries))?;
+        fs::rename(&temp, file)
+    }
+}
+
+/// Moves `path` to the most recent end, dropping the oldest entries past
+/// the limit.
+fn upsert(entries: &mut Vec<(PathBuf, ScrollAnchor)>, path: PathBuf, anchor: ScrollAnchor) {
+    entries.retain(|(entry, _)| *entry != path);
+    entries.push((path, anchor));
+    let excess = entries.len().saturating_sub(POSITION_LIMIT);
+    entries.drain(..excess);
+}
+
+fn key_for(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// One line per file: path, source line, line delta, row offset, then the
+/// heading's source line and title (both empty without a heading).
+fn format(entries: &[(PathBuf, ScrollAnchor)]) -> String {
+    let mut text = String::new();
+    for (path, anchor) in entries {
+        let Some(path) = path.to_str().filter(|path| !path.contains(['\t', '\n'])) else {
+            continue;
+        };
+        let (heading_line, title) = match &anchor.heading {
+            Some(heading) => (
+                heading.source_line.to_string(),
+                heading.title.replace(['\t', '\n'], " "),
+            ),
+            None => (String::new(), String::new()),
+        };
+        text.push_str(&format!(
+            "{path}\t{}\t{}\t{}\t{heading_line}\t{title}\n",
+            anchor.source_line, anchor.line_delta, anchor.row_offset
+        ));
+    }
+    text
+}
+
+fn parse(text: &str) -> Vec<(PathBuf, ScrollAnchor)> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<(PathBuf, ScrollAnchor)> {
+    let mut fields = line.split('\t');
+    let path = PathBuf::from(fields.next()?);
+    let mut number = || fields.next()?.parse::<usize>().ok();
+    let (source_line, line_delta, row_offset) = (number()?, number()?, number()?);
+    let heading = match (fields.next(), fields.next()) {
+        (Some(heading_line), Some(title)) if !heading_line.is_empty() => Some(HeadingAnchor {
+            title: title.to_string(),
+            source_line: heading_line.parse().ok()?,
+        }),
+        _ => None,
+    };
+    let anchor = ScrollAnchor {
+        heading,
+        source_line,
+        line_delta,
+        row_offset,
+    };
+    Some((path, anchor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_through_tsv() {
+        let entries = vec![
+            (
+                PathBuf::from("/docs/spec.md"),
+                ScrollAnchor {
+                    heading: Some(HeadingAnchor {
+                        title: String::from("Error\thandling"),
+                        source_line: 120,
+                    }),
+                    source_line: 131,
+                    line_delta: 1,
+                    row_offset: 2,
+                },
+            ),
+            (
+                PathBuf::from("/docs/notes.md"),
+                ScrollAnchor {
+                    heading: None,
+                    source_line: 4,
+                    line_delta: 0,
+                    row_offset: 0,
+                },
+            ),
+        ];
+        let parsed = parse(&format(&entries));
+        assert_eq!(parsed.len(), 2);
+        let heading = parsed[0].1.heading.as_ref().unwrap();
+        assert_eq!(heading.title, "Error handling");
+        assert_eq!(parsed[0].1.source_line, 131);
+        assert_eq!(parsed[1], entries[1]);
+        assert!(parse("garbage\nline\twith\tfew").is_empty());
+    }
+
+    #[test]
+    fn save_keeps_entries_written_by_other_viewers() {
+        let at = |source_line| ScrollAnchor {
+            heading: None,
+            source_line,
+            line_delta: 0,
+            row_offset: 0,
+        };
+        let dir = env::temp_dir().join(format!("md-viewer-state-{}", std::process::id()));
+        let file = dir.join("positions.tsv");
+        let (a, b, c) = (
+            PathBuf::from("/a.md"),
+            PathBuf::from("/b.md"),
+            PathBuf::from("/c.md"),
+        );
+        let mut ours = Positions {
+            file: Some(file.clone()),
+            entries: vec![(a.clone(), at(1)), (b.clone(), at(2))],
+            restore: true,
+            recorded: HashSet::new(),
+        };
+        ours.recorded.insert(c.clone());
+        ours.entries.push((c.clone(), at(3)));
+        // Another viewer saved a newer position for `b` meanwhile.
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, format(&[(a.clone(), at(1)), (b.clone(), at(20))])).unwrap();
+
+        ours.save().unwrap();
+        let saved = parse(&fs::read_to_string(&file).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(saved, vec![(a, at(1)), (b, at(20)), (c, at(3))]);
+    }
+}
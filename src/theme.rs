@@ -0,0 +1,435 @@
+use pulldown_cmark::HeadingLevel;
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::highlight::TokenKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Text style for H1 through H6.
+    pub heading_text: [Style; 6],
+    /// Band `(background, foreground)` painted behind H1 through H6.
+    pub heading_band: [(Color, Color); 6],
+    pub code_fg: Color,
+    pub code_bg: Color,
+    pub code_border: Color,
+    pub inline_code: Style,
+    pub link: Color,
+    pub blockquote_text: Color,
+    pub blockquote_bars: [Color; 4],
+    pub list_marker: Color,
+    pub rule: Color,
+    /// Image labels (icon and alt text) shown in place of pictures.
+    pub image: Color,
+    pub syntax: SyntaxTheme,
+    pub ui: UiTheme,
+}
+
+/// Colors of the viewer around the document: panes, bars and popups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UiTheme {
+    pub search_match: Style,
+    pub current_match: Style,
+    /// File name, focused borders, the scrollbar thumb and help headers.
+    pub accent: Color,
+    /// Line counts and the reading position.
+    pub secondary: Color,
+    /// Unfocused borders, the scrollbar track and other quiet text.
+    pub muted: Color,
+    /// Status messages and the outline's current-heading marker.
+    pub highlight: Color,
+    pub popup: Style,
+    /// Outline entries for H1, H2, H3 and anything deeper.
+    pub outline_levels: [Color; 4],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyntaxTheme {
+    pub keyword: Style,
+    pub type_name: Style,
+    pub function: Style,
+    pub string: Style,
+    pub number: Style,
+    pub comment: Style,
+    pub key: Style,
+    pub variable: Style,
+    pub operator: Style,
+    pub inserted: Style,
+    pub deleted: Style,
+    pub header: Style,
+    pub hunk: Style,
+}
+
+pub const THEME_NAMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<Theme> {
+        match name.to_ascii_lowercase().as_str() {
+            "dark" => Some(DARK),
+            "light" => Some(LIGHT),
+            "high-contrast" | "contrast" | "hc" => Some(HIGH_CONTRAST),
+            _ => None,
+        }
+    }
+
+    pub fn heading_text(&self, level: HeadingLevel) -> Style {
+        self.heading_text[level_index(level)]
+    }
+
+    pub fn heading_band(&self, level: HeadingLevel) -> (Color, Color) {
+        self.heading_band[level_index(level)]
+    }
+
+    pub fn blockquote_bar(&self, depth: usize) -> Color {
+        self.blockquote_bars[depth % self.blockquote_bars.len()]
+    }
+
+    pub fn code_block(&self) -> Style {
+        Style::new().fg(self.code_fg).bg(self.code_bg)
+    }
+
+    pub fn code_border(&self) -> Style {
+        Style::new()
+            .fg(self.code_border)
+            .bg(self.code_bg)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    /// Returns a copy with every color passed through `map`, used to fit the
+    /// palette to terminals without truecolor support.
+    pub fn map_colors(mut self, map: impl Fn(Color) -> Color) -> Theme {
+        let style = |style: Style| Style {
+            fg: style.fg.map(&map),
+            bg: style.bg.map(&map),
+            ..style
+        };
+        for heading in &mut self.heading_text {
+            *heading = style(*heading);
+        }
+        for (bg, fg) in &mut self.heading_band {
+            *bg = map(*bg);
+            *fg = map(*fg);
+        }
+        self.code_fg = map(self.code_fg);
+        self.code_bg = map(self.code_bg);
+        self.code_border = map(self.code_border);
+        self.inline_code = style(self.inline_code);
+        self.link = map(self.link);
+        self.blockquote_text = map(self.blockquote_text);
+        for bar in &mut self.blockquote_bars {
+            *bar = map(*bar);
+        }
+        self.list_marker = map(self.list_marker);
+        self.rule = map(self.rule);
+        self.image = map(self.image);
+        let syntax = &mut self.syntax;
+        for token in [
+            &mut syntax.keyword,
+            &mut syntax.type_name,
+            &mut syntax.function,
+            &mut syntax.string,
+            &mut syntax.number,
+            &mut syntax.comment,
+            &mut syntax.key,
+            &mut syntax.variable,
+            &mut syntax.operator,
+            &mut syntax.inserted,
+            &mut syntax.deleted,
+            &mut syntax.header,
+            &mut syntax.hunk,
+        ] {
+            *token = style(*token);
+        }
+        let ui = &mut self.ui;
+        ui.search_match = style(ui.search_match);
+        ui.current_match = style(ui.current_match);
+        ui.popup = style(ui.popup);
+        for color in [
+            &mut ui.accent,
+            &mut ui.secondary,
+            &mut ui.muted,
+            &mut ui.highlight,
+        ] {
+            *color = map(*color);
+        }
+        for color in &mut ui.outline_levels {
+            *color = map(*color);
+        }
+        self
+    }
+
+    pub fn outline_level(&self, level: HeadingLevel) -> Color {
+        self.ui.outline_levels[level_index(level).min(3)]
+    }
+
+    pub fn token(&self, kind: TokenKind) -> Style {
+        let syntax = &self.syntax;
+        match kind {
+            TokenKind::Plain => Style::new(),
+            TokenKind::Keyword => syntax.keyword,
+            TokenKind::Type => syntax.type_name,
+            TokenKind::Function => syntax.function,
+            TokenKind::String => syntax.string,
+            TokenKind::Number | TokenKind::Constant => syntax.number,
+            TokenKind::Comment => syntax.comment,
+            TokenKind::Key => syntax.key,
+            TokenKind::Variable => syntax.variable,
+            TokenKind::Operator => syntax.operator,
+            TokenKind::Inserted => syntax.inserted,
+            TokenKind::Deleted => syntax.deleted,
+            TokenKind::Header => syntax.header,
+            TokenKind::Hunk => syntax.hunk,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        DARK
+    }
+}
+
+fn level_index(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 0,
+        HeadingLevel::H2 => 1,
+        HeadingLevel::H3 => 2,
+        HeadingLevel::H4 => 3,
+        HeadingLevel::H5 => 4,
+        HeadingLevel::H6 => 5,
+    }
+}
+
+const fn fg(color: Color) -> Style {
+    Style::new().fg(color)
+}
+
+pub const DARK: Theme = Theme {
+    name: "dark",
+    heading_text: [
+        fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        fg(Color::LightBlue).add_modifier(Modifier::BOLD),
+        fg(Color::LightMagenta).add_modifier(Modifier::BOLD),
+        fg(Color::Magenta),
+        fg(Color::Magenta).add_modifier(Modifier::ITALIC),
+        fg(Color::Gray).add_modifier(Modifier::ITALIC),
+    ],
+    heading_band: [
+        (Color::Rgb(48, 52, 70), Color::Rgb(235, 235, 245)),
+        (Color::Rgb(40, 44, 60), Color::Rgb(225, 225, 235)),
+        (Color::Rgb(35, 39, 54), Color::Rgb(210, 210, 225)),
+        (Color::Rgb(30, 34, 48), Color::Rgb(200, 200, 215)),
+        (Color::Rgb(28, 32, 44), Color::Rgb(190, 190, 205)),
+        (Color::Rgb(24, 28, 38), Color::Rgb(180, 180, 195)),
+    ],
+    code_fg: Color::Rgb(225, 228, 235),
+    code_bg: Color::Rgb(12, 16, 26),
+    code_border: Color::Rgb(150, 160, 175),
+    inline_code: fg(Color::Yellow).add_modifier(Modifier::DIM),
+    link: Color::Cyan,
+    blockquote_text: Color::Gray,
+    blockquote_bars: [
+        Color::Rgb(255, 200, 150),
+        Color::Rgb(230, 170, 120),
+        Color::Rgb(200, 140, 110),
+        Color::Rgb(170, 120, 100),
+    ],
+    list_marker: Color::Gray,
+    rule: Color::DarkGray,
+    image: Color::Magenta,
+    syntax: SyntaxTheme {
+        keyword: fg(Color::Rgb(198, 120, 221)),
+        type_name: fg(Color::Rgb(229, 192, 123)),
+        function: fg(Color::Rgb(97, 175, 239)),
+        string: fg(Color::Rgb(152, 195, 121)),
+        number: fg(Color::Rgb(209, 154, 102)),
+        comment: fg(Color::Rgb(110, 118, 135)).add_modifier(Modifier::ITALIC),
+        key: fg(Color::Rgb(224, 108, 117)),
+        variable: fg(Color::Rgb(86, 182, 194)),
+        operator: fg(Color::Rgb(171, 178, 191)),
+        inserted: fg(Color::Rgb(152, 195, 121)),
+        deleted: fg(Color::Rgb(224, 108, 117)),
+        header: fg(Color::Rgb(229, 192, 123)).add_modifier(Modifier::BOLD),
+        hunk: fg(Color::Rgb(86, 182, 194)),
+    },
+    ui: UiTheme {
+        search_match: fg(Color::Black).bg(Color::Rgb(150, 130, 60)),
+        current_match: fg(Color::Black)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+        accent: Color::Cyan,
+        secondary: Color::Gray,
+        muted: Color::DarkGray,
+        highlight: Color::Yellow,
+        popup: Style::new().bg(Color::Black),
+        outline_levels: [
+            Color::Cyan,
+            Color::LightBlue,
+            Color::LightMagenta,
+            Color::Gray,
+        ],
+    },
+};
+
+pub const LIGHT: Theme = Theme {
+    name: "light",
+    heading_text: [
+        fg(Color::Rgb(0, 95, 135)).add_modifier(Modifier::BOLD),
+        fg(Color::Rgb(30, 80, 160)).add_modifier(Modifier::BOLD),
+        fg(Color::Rgb(130, 40, 140)).add_modifier(Modifier::BOLD),
+        fg(Color::Rgb(110, 40, 120)),
+        fg(Color::Rgb(110, 40, 120)).add_modifier(Modifier::ITALIC),
+        fg(Color::Rgb(90, 90, 90)).add_modifier(Modifier::ITALIC),
+    ],
+    heading_band: [
+        (Color::Rgb(218, 226, 242), Color::Rgb(20, 30, 60)),
+        (Color::Rgb(226, 232, 245), Color::Rgb(30, 40, 70)),
+        (Color::Rgb(232, 237, 247), Color::Rgb(40, 50, 80)),
+        (Color::Rgb(237, 241, 249), Color::Rgb(50, 60, 90)),
+        (Color::Rgb(241, 244, 250), Color::Rgb(60, 70, 95)),
+        (Color::Rgb(245, 247, 252), Color::Rgb(70, 80, 100)),
+    ],
+    code_fg: Color::Rgb(40, 44, 52),
+    code_bg: Color::Rgb(240, 241, 245),
+    code_border: Color::Rgb(120, 125, 135),
+    inline_code: fg(Color::Rgb(160, 80, 0)),
+    link: Color::Rgb(0, 90, 180),
+    blockquote_text: Color::Rgb(90, 90, 90),
+    blockquote_bars: [
+        Color::Rgb(200, 120, 60),
+        Color::Rgb(170, 100, 60),
+        Color::Rgb(140, 90, 60),
+        Color::Rgb(120, 80, 60),
+    ],
+    list_marker: Color::Rgb(100, 100, 100),
+    rule: Color::Rgb(180, 180, 180),
+    image: Color::Rgb(130, 60, 160),
+    syntax: SyntaxTheme {
+        keyword: fg(Color::Rgb(166, 38, 164)),
+        type_name: fg(Color::Rgb(193, 132, 1)),
+        function: fg(Color::Rgb(64, 120, 242)),
+        string: fg(Color::Rgb(80, 161, 79)),
+        number: fg(Color::Rgb(152, 104, 1)),
+        comment: fg(Color::Rgb(160, 161, 167)).add_modifier(Modifier::ITALIC),
+        key: fg(Color::Rgb(228, 86, 73)),
+        variable: fg(Color::Rgb(1, 132, 188)),
+        operator: fg(Color::Rgb(56, 58, 66)),
+        inserted: fg(Color::Rgb(80, 161, 79)),
+        deleted: fg(Color::Rgb(228, 86, 73)),
+        header: fg(Color::Rgb(193, 132, 1)).add_modifier(Modifier::BOLD),
+        hunk: fg(Color::Rgb(1, 132, 188)),
+    },
+    ui: UiTheme {
+        search_match: fg(Color::Black).bg(Color::Rgb(250, 232, 160)),
+        current_match: fg(Color::Black)
+            .bg(Color::Rgb(255, 200, 60))
+            .add_modifier(Modifier::BOLD),
+        accent: Color::Rgb(0, 110, 160),
+        secondary: Color::Rgb(100, 100, 100),
+        muted: Color::Rgb(165, 165, 165),
+        highlight: Color::Rgb(170, 100, 0),
+        popup: fg(Color::Rgb(40, 44, 52)).bg(Color::Rgb(248, 248, 250)),
+        outline_levels: [
+            Color::Rgb(0, 95, 135),
+            Color::Rgb(30, 80, 160),
+            Color::Rgb(130, 40, 140),
+            Color::Rgb(90, 90, 90),
+        ],
+    },
+};
+
+/// Sticks to the 16 named colors so it follows the terminal's own palette.
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "high-contrast",
+    heading_text: [
+        fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+        fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+        fg(Color::White).add_modifier(Modifier::BOLD),
+        fg(Color::White).add_modifier(Modifier::UNDERLINED),
+        fg(Color::White).add_modifier(Modifier::ITALIC),
+        fg(Color::White),
+    ],
+    heading_band: [
+        (Color::Blue, Color::White),
+        (Color::Magenta, Color::White),
+        (Color::Black, Color::White),
+        (Color::Black, Color::LightCyan),
+        (Color::Black, Color::LightYellow),
+        (Color::Black, Color::Gray),
+    ],
+    code_fg: Color::White,
+    code_bg: Color::Black,
+    code_border: Color::White,
+    inline_code: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+    link: Color::LightCyan,
+    blockquote_text: Color::White,
+    blockquote_bars: [
+        Color::LightYellow,
+        Color::LightGreen,
+        Color::LightCyan,
+        Color::LightMagenta,
+    ],
+    list_marker: Color::White,
+    rule: Color::White,
+    image: Color::LightMagenta,
+    syntax: SyntaxTheme {
+        keyword: fg(Color::LightMagenta).add_modifier(Modifier::BOLD),
+        type_name: fg(Color::LightYellow),
+        function: fg(Color::LightBlue),
+        string: fg(Color::LightGreen),
+        number: fg(Color::LightRed),
+        comment: fg(Color::Gray).add_modifier(Modifier::ITALIC),
+        key: fg(Color::LightCyan),
+        variable: fg(Color::LightCyan),
+        operator: fg(Color::White),
+        inserted: fg(Color::LightGreen),
+        deleted: fg(Color::LightRed),
+        header: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+        hunk: fg(Color::LightCyan),
+    },
+    ui: UiTheme {
+        search_match: fg(Color::Black).bg(Color::Cyan),
+        current_match: fg(Color::Black)
+            .bg(Color::LightYellow)
+            .add_modifier(Modifier::BOLD),
+        accent: Color::LightCyan,
+        secondary: Color::White,
+        muted: Color::Gray,
+        highlight: Color::LightYellow,
+        popup: fg(Color::White).bg(Color::Black),
+        outline_levels: [
+            Color::LightYellow,
+            Color::LightCyan,
+            Color::White,
+            Color::White,
+        ],
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_named_theme_resolves() {
+        for name in THEME_NAMES {
+            assert_eq!(Theme::by_name(name).map(|theme| theme.name), Some(name));
+        }
+        assert!(Theme::by_name("solarized").is_none());
+    }
+
+    #[test]
+    fn heading_bands_are_distinct() {
+        for theme in [DARK, LIGHT, HIGH_CONTRAST] {
+            assert!(theme.heading_band.windows(2).all(|pair| pair[0] != pair[1]));
+        }
+    }
+
+    #[test]
+    fn the_current_search_match_stands_out() {
+        for theme in [DARK, LIGHT, HIGH_CONTRAST] {
+            assert_ne!(theme.ui.current_match.bg, theme.ui.search_match.bg);
+            assert_ne!(theme.ui.popup.bg, None);
+        }
+    }
+}